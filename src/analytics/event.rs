@@ -0,0 +1,55 @@
+use crate::station::types::StationShape;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum EventKind {
+    Spawn,
+    Board,
+    Alight,
+    Deliver,
+    Abandon,
+}
+
+impl EventKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            EventKind::Spawn => "spawn",
+            EventKind::Board => "board",
+            EventKind::Alight => "alight",
+            EventKind::Deliver => "deliver",
+            EventKind::Abandon => "abandon",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Event {
+    pub kind: EventKind,
+    pub station: usize,
+    pub vehicle: Option<usize>,
+    pub destination: StationShape,
+}
+
+impl Event {
+    pub fn new(
+        kind: EventKind,
+        station: usize,
+        vehicle: Option<usize>,
+        destination: StationShape,
+    ) -> Self {
+        Event {
+            kind,
+            station,
+            vehicle,
+            destination,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Snapshot {
+    pub time: f32,
+    pub subject: &'static str,
+    pub id: usize,
+    pub count: usize,
+    pub capacity: usize,
+}
@@ -0,0 +1,62 @@
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use ggez::GameResult;
+
+use super::handler::Analytics;
+
+pub const DEFAULT_EXPORT_DIR: &str = "metrics";
+
+pub fn export(analytics: &Analytics, dir: impl AsRef<Path>) -> GameResult {
+    let dir = dir.as_ref();
+    fs::create_dir_all(dir)?;
+
+    let mut csv = BufWriter::new(File::create(dir.join("events.csv"))?);
+    let mut jsonl = BufWriter::new(File::create(dir.join("events.jsonl"))?);
+    writeln!(csv, "time,event,station,vehicle,destination")?;
+    for (time, event) in analytics.events() {
+        let vehicle = event.vehicle.map(|v| v.to_string());
+        writeln!(
+            csv,
+            "{:.3},{},{},{},{:?}",
+            time,
+            event.kind.name(),
+            event.station,
+            vehicle.as_deref().unwrap_or_default(),
+            event.destination
+        )?;
+        writeln!(
+            jsonl,
+            "{{\"time\":{:.3},\"event\":\"{}\",\"station\":{},\"vehicle\":{},\"destination\":\"{:?}\"}}",
+            time,
+            event.kind.name(),
+            event.station,
+            vehicle.as_deref().unwrap_or("null"),
+            event.destination
+        )?;
+    }
+    csv.flush()?;
+    jsonl.flush()?;
+
+    let mut csv = BufWriter::new(File::create(dir.join("snapshots.csv"))?);
+    let mut jsonl = BufWriter::new(File::create(dir.join("snapshots.jsonl"))?);
+    writeln!(csv, "time,subject,id,count,capacity")?;
+    for snapshot in analytics.snapshots() {
+        writeln!(
+            csv,
+            "{:.3},{},{},{},{}",
+            snapshot.time, snapshot.subject, snapshot.id, snapshot.count, snapshot.capacity
+        )?;
+        writeln!(
+            jsonl,
+            "{{\"time\":{:.3},\"subject\":\"{}\",\"id\":{},\"count\":{},\"capacity\":{}}}",
+            snapshot.time, snapshot.subject, snapshot.id, snapshot.count, snapshot.capacity
+        )?;
+    }
+    csv.flush()?;
+    jsonl.flush()?;
+    Ok(())
+}
@@ -0,0 +1,188 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::{station::handler::StationHandler, vehicle::handler::VehicleHandler};
+
+use super::{
+    Counters, MAX_EVENTS, MAX_SAMPLES, MAX_SNAPSHOTS, SAMPLE_INTERVAL, Sample, Subject,
+    event::{Event, Snapshot},
+};
+
+pub struct Analytics {
+    elapsed: f32,
+    next_sample: f32,
+    totals: HashMap<Subject, Counters>,
+    series: HashMap<Subject, Vec<Sample>>,
+    events: VecDeque<(f32, Event)>,
+    snapshots: VecDeque<Snapshot>,
+}
+
+impl Analytics {
+    pub fn new() -> Self {
+        Analytics {
+            elapsed: 0.0,
+            next_sample: SAMPLE_INTERVAL,
+            totals: HashMap::new(),
+            series: HashMap::new(),
+            events: VecDeque::new(),
+            snapshots: VecDeque::new(),
+        }
+    }
+
+    pub fn series(&self, subject: Subject) -> &[Sample] {
+        self.series
+            .get(&subject)
+            .map(|series| series.as_slice())
+            .unwrap_or_default()
+    }
+
+    pub fn totals(&self, subject: Subject) -> Counters {
+        self.totals.get(&subject).copied().unwrap_or_default()
+    }
+
+    pub fn events(&self) -> &VecDeque<(f32, Event)> {
+        &self.events
+    }
+
+    pub fn snapshots(&self) -> &VecDeque<Snapshot> {
+        &self.snapshots
+    }
+
+    // Station ids shift when a station is placed or removed; history of a removed one is
+    // dropped.
+    pub fn renumber_stations(&mut self, renumber: impl Fn(usize) -> Option<usize>) {
+        self.events.retain_mut(|(_, event)| {
+            renumber(event.station)
+                .map(|id| event.station = id)
+                .is_some()
+        });
+        self.snapshots.retain_mut(|snapshot| {
+            snapshot.subject != "station"
+                || renumber(snapshot.id).map(|id| snapshot.id = id).is_some()
+        });
+        let subject = |subject: Subject| match subject {
+            Subject::Station(id) => renumber(id).map(Subject::Station),
+            other => Some(other),
+        };
+        self.totals = self
+            .totals
+            .drain()
+            .filter_map(|(key, value)| Some((subject(key)?, value)))
+            .collect();
+        self.series = self
+            .series
+            .drain()
+            .filter_map(|(key, value)| Some((subject(key)?, value)))
+            .collect();
+    }
+
+    // A train placed later may take over the id, and must not be measured against this one.
+    pub fn forget_vehicle(&mut self, vehicle: usize) {
+        self.totals.remove(&Subject::Vehicle(vehicle));
+    }
+
+    pub fn subjects(&self) -> Vec<Subject> {
+        let mut subjects: Vec<Subject> = self.series.keys().copied().collect();
+        subjects.sort_by_key(|subject| match *subject {
+            Subject::Network => (0, 0),
+            Subject::Route(id) => (1, id),
+            Subject::Station(id) => (2, id),
+            Subject::Vehicle(id) => (3, id),
+        });
+        subjects
+    }
+
+    pub fn update(&mut self, stations: &mut StationHandler, vehicles: &VehicleHandler, delta: f32) {
+        self.elapsed += delta;
+        let elapsed = self.elapsed;
+        self.events.extend(
+            stations
+                .drain_events()
+                .into_iter()
+                .map(|event| (elapsed, event)),
+        );
+        if self.events.len() > MAX_EVENTS {
+            self.events.drain(..self.events.len() - MAX_EVENTS);
+        }
+        if self.elapsed < self.next_sample {
+            return;
+        }
+        self.next_sample += SAMPLE_INTERVAL;
+
+        // Trains change lines and stations come and go, so lines and the network add up what
+        // their members recorded since the last sample instead of re-summing running totals.
+        let mut current: Vec<(Subject, Counters, Option<f32>)> = vec![];
+        let mut network = Counters::default();
+        for station in stations.stations() {
+            let subject = Subject::Station(station.id());
+            let recorded = station.counters().since(&self.totals(subject));
+            network.add(&recorded);
+            self.totals.insert(subject, *station.counters());
+            current.push((subject, recorded, None));
+            self.snapshots.push_back(Snapshot {
+                time: self.elapsed,
+                subject: "station",
+                id: station.id(),
+                count: station.waiting_count(),
+                capacity: station.capacity(),
+            });
+        }
+
+        let mut routes: HashMap<usize, (Counters, usize, usize)> = HashMap::new();
+        let (mut passengers, mut capacity) = (0, 0);
+        for vehicle in vehicles.vehicles() {
+            let load = vehicle.passengers().len();
+            self.snapshots.push_back(Snapshot {
+                time: self.elapsed,
+                subject: "vehicle",
+                id: vehicle.id(),
+                count: load,
+                capacity: vehicle.capacity(),
+            });
+            let subject = Subject::Vehicle(vehicle.id());
+            let recorded = vehicle.counters().since(&self.totals(subject));
+            self.totals.insert(subject, *vehicle.counters());
+            let load_factor = load as f32 / vehicle.capacity().max(1) as f32;
+            current.push((subject, recorded, Some(load_factor)));
+            let route = routes.entry(vehicle.route()).or_default();
+            route.0.add(&recorded);
+            route.1 += load;
+            route.2 += vehicle.capacity();
+            passengers += load;
+            capacity += vehicle.capacity();
+        }
+        for (id, (recorded, load, capacity)) in routes {
+            let load_factor = load as f32 / capacity.max(1) as f32;
+            self.totals
+                .entry(Subject::Route(id))
+                .or_default()
+                .add(&recorded);
+            current.push((Subject::Route(id), recorded, Some(load_factor)));
+        }
+        let load_factor = passengers as f32 / capacity.max(1) as f32;
+        self.totals
+            .entry(Subject::Network)
+            .or_default()
+            .add(&network);
+        current.push((Subject::Network, network, Some(load_factor)));
+
+        if self.snapshots.len() > MAX_SNAPSHOTS {
+            self.snapshots.drain(..self.snapshots.len() - MAX_SNAPSHOTS);
+        }
+
+        for (subject, recorded, load_factor) in current {
+            let totals = self.totals(subject);
+            let series = self.series.entry(subject).or_default();
+            series.push(Sample {
+                time: self.elapsed,
+                boarded: recorded.boarded(),
+                alighted: recorded.alighted(),
+                average_wait: totals.average_wait(),
+                average_trip_time: totals.average_trip_time(),
+                load_factor: load_factor.unwrap_or_default(),
+            });
+            if series.len() > MAX_SAMPLES {
+                series.remove(0);
+            }
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use crate::passenger::Passenger;
+
+pub mod event;
+pub mod export;
+pub mod handler;
+
+pub const SAMPLE_INTERVAL: f32 = 5.0;
+pub const MAX_SAMPLES: usize = 720;
+// Oldest entries are dropped first, so an export covers the most recent part of a long run.
+pub const MAX_EVENTS: usize = 200_000;
+pub const MAX_SNAPSHOTS: usize = 100_000;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Subject {
+    Network,
+    Route(usize),
+    Station(usize),
+    Vehicle(usize),
+}
+
+#[derive(Clone, Copy, Default, Debug)]
+pub struct Counters {
+    boarded: u32,
+    alighted: u32,
+    total_wait: f32,
+    trips: u32,
+    total_trip_time: f32,
+}
+
+impl Counters {
+    pub fn boarded(&self) -> u32 {
+        self.boarded
+    }
+
+    pub fn alighted(&self) -> u32 {
+        self.alighted
+    }
+
+    pub fn average_wait(&self) -> f32 {
+        if self.boarded == 0 {
+            0.0
+        } else {
+            self.total_wait / self.boarded as f32
+        }
+    }
+
+    pub fn average_trip_time(&self) -> f32 {
+        if self.trips == 0 {
+            0.0
+        } else {
+            self.total_trip_time / self.trips as f32
+        }
+    }
+
+    pub fn record_boarding(&mut self, passenger: &Passenger) {
+        self.boarded += 1;
+        self.total_wait += passenger.wait_time();
+    }
+
+    pub fn record_alighting(&mut self, passenger: &Passenger, delivered: bool) {
+        self.alighted += 1;
+        if delivered {
+            self.trips += 1;
+            self.total_trip_time += passenger.trip_time();
+        }
+    }
+
+    // What was recorded after `earlier` was taken. Counters only grow, so smaller ones belong to
+    // a train that took over a removed one's id and everything on them is new.
+    pub fn since(&self, earlier: &Counters) -> Counters {
+        if self.boarded < earlier.boarded
+            || self.alighted < earlier.alighted
+            || self.trips < earlier.trips
+        {
+            return *self;
+        }
+        Counters {
+            boarded: self.boarded - earlier.boarded,
+            alighted: self.alighted - earlier.alighted,
+            total_wait: self.total_wait - earlier.total_wait,
+            trips: self.trips - earlier.trips,
+            total_trip_time: self.total_trip_time - earlier.total_trip_time,
+        }
+    }
+
+    pub fn add(&mut self, other: &Counters) {
+        self.boarded += other.boarded;
+        self.alighted += other.alighted;
+        self.total_wait += other.total_wait;
+        self.trips += other.trips;
+        self.total_trip_time += other.total_trip_time;
+    }
+}
+
+#[derive(Clone, Copy, Default, Debug)]
+pub struct Sample {
+    pub time: f32,
+    pub boarded: u32,
+    pub alighted: u32,
+    pub average_wait: f32,
+    pub average_trip_time: f32,
+    pub load_factor: f32,
+}
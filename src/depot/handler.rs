@@ -0,0 +1,86 @@
+use ggez::{
+    Context,
+    glam::Vec2,
+    graphics::{Canvas, DrawMode, DrawParam, Mesh, MeshBuilder, Rect},
+};
+
+use crate::{shape::palette::ShapePalette, utils::colors::Colors};
+
+use super::Depot;
+
+pub const DEPOT_SIZE: f32 = 16.0;
+pub const DEPOT_SERVICE_RADIUS: f32 = 150.0;
+
+pub struct DepotHandler {
+    depots: Vec<Depot>,
+    mesh: Mesh,
+}
+
+impl DepotHandler {
+    pub fn new(ctx: &Context) -> Self {
+        DepotHandler {
+            depots: vec![],
+            mesh: Self::build_mesh(
+                ctx,
+                ShapePalette::new(Colors::depot_fill(), Colors::depot_outline()),
+            ),
+        }
+    }
+
+    fn build_mesh(ctx: &Context, palette: ShapePalette) -> Mesh {
+        let rect = Rect::new(-DEPOT_SIZE / 2.0, -DEPOT_SIZE / 2.0, DEPOT_SIZE, DEPOT_SIZE);
+        Mesh::from_data(
+            ctx,
+            MeshBuilder::new()
+                .rectangle(DrawMode::fill(), rect, palette.filled())
+                .expect("Error creating mesh for depot.")
+                .rectangle(DrawMode::stroke(3.0), rect, palette.outline())
+                .expect("Error creating mesh for depot.")
+                .build(),
+        )
+    }
+
+    pub fn set_palette(&mut self, ctx: &Context, palette: ShapePalette) {
+        self.mesh = Self::build_mesh(ctx, palette);
+    }
+
+    pub fn depots(&self) -> &[Depot] {
+        &self.depots
+    }
+
+    pub fn add_depot(&mut self, position: Vec2) {
+        self.depots.push(Depot::new(position, DEPOT_SERVICE_RADIUS));
+    }
+
+    pub fn insert_depot(&mut self, index: usize, position: Vec2) {
+        self.depots
+            .insert(index, Depot::new(position, DEPOT_SERVICE_RADIUS));
+    }
+
+    pub fn remove_depot(&mut self, index: usize) -> Vec2 {
+        self.depots.remove(index).position()
+    }
+
+    pub fn depot_at(&self, position: Vec2) -> Option<usize> {
+        self.depots
+            .iter()
+            .position(|depot| depot.position().distance(position) <= DEPOT_SIZE)
+    }
+
+    pub fn nearest_serving(&self, position: Vec2) -> Option<&Depot> {
+        self.depots
+            .iter()
+            .filter(|depot| depot.serves(position))
+            .min_by(|a, b| {
+                a.position()
+                    .distance(position)
+                    .total_cmp(&b.position().distance(position))
+            })
+    }
+
+    pub fn draw(&self, canvas: &mut Canvas) {
+        for depot in self.depots.iter() {
+            canvas.draw(&self.mesh, DrawParam::default().dest(depot.position()));
+        }
+    }
+}
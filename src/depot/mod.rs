@@ -0,0 +1,25 @@
+use ggez::glam::Vec2;
+
+pub mod handler;
+
+pub struct Depot {
+    position: Vec2,
+    service_radius: f32,
+}
+
+impl Depot {
+    pub fn new(position: Vec2, service_radius: f32) -> Self {
+        Depot {
+            position,
+            service_radius,
+        }
+    }
+
+    pub fn position(&self) -> Vec2 {
+        self.position
+    }
+
+    pub fn serves(&self, position: Vec2) -> bool {
+        self.position.distance(position) <= self.service_radius
+    }
+}
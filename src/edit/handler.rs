@@ -0,0 +1,65 @@
+use super::{Command, EditError, Network};
+
+pub const MAX_HISTORY: usize = 200;
+
+pub struct EditHistory {
+    undo: Vec<Command>,
+    redo: Vec<Command>,
+}
+
+impl EditHistory {
+    pub fn new() -> Self {
+        EditHistory {
+            undo: vec![],
+            redo: vec![],
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    pub fn execute(&mut self, command: Command, network: &mut Network) -> Result<(), EditError> {
+        let inverse = command.apply(network)?;
+        self.undo.push(inverse);
+        if self.undo.len() > MAX_HISTORY {
+            self.undo.remove(0);
+        }
+        self.redo.clear();
+        Ok(())
+    }
+
+    pub fn undo(&mut self, network: &mut Network) -> Result<bool, EditError> {
+        Self::replay(&mut self.undo, &mut self.redo, network)
+    }
+
+    pub fn redo(&mut self, network: &mut Network) -> Result<bool, EditError> {
+        Self::replay(&mut self.redo, &mut self.undo, network)
+    }
+
+    // Applies the newest command of one stack and files its inverse on the other. A command that
+    // fails stays where it was.
+    fn replay(
+        from: &mut Vec<Command>,
+        to: &mut Vec<Command>,
+        network: &mut Network,
+    ) -> Result<bool, EditError> {
+        let Some(command) = from.pop() else {
+            return Ok(false);
+        };
+        match command.clone().apply(network) {
+            Ok(inverse) => {
+                to.push(inverse);
+                Ok(true)
+            }
+            Err(e) => {
+                from.push(command);
+                Err(e)
+            }
+        }
+    }
+}
@@ -0,0 +1,623 @@
+use std::cmp::Ordering;
+
+use ggez::{Context, glam::Vec2};
+
+use crate::{
+    analytics::handler::Analytics,
+    depot::handler::DepotHandler,
+    route::{Route, handler::RouteHandler, stop::Stop},
+    station::{StationSpec, handler::StationHandler},
+    vehicle::{Vehicle, handler::VehicleHandler, metro::Metro},
+};
+use mode::GameMode;
+
+pub mod handler;
+pub mod mode;
+
+pub const STARTING_LINES: u32 = 3;
+pub const STARTING_TRAINS: u32 = 3;
+pub const STARTING_CARRIAGES: u32 = 2;
+pub const MAX_CARRIAGES: usize = 4;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Resource {
+    Line,
+    Train,
+    Carriage,
+}
+
+impl Resource {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Resource::Line => "lines",
+            Resource::Train => "trains",
+            Resource::Carriage => "carriages",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum EditError {
+    Exhausted(Resource),
+    InvalidRoute,
+    InvalidVehicle,
+    InvalidStop,
+    TooFewStops,
+    NotATerminus,
+    RouteInUse,
+    CarriageLimit,
+    NotAllowed,
+    InvalidStation,
+    Occupied,
+    StationInUse,
+    InvalidDepot,
+}
+
+impl EditError {
+    pub fn description(&self) -> String {
+        match self {
+            EditError::Exhausted(resource) => format!("No {} left", resource.name()),
+            EditError::InvalidRoute => String::from("No such line"),
+            EditError::InvalidVehicle => String::from("No such train"),
+            EditError::InvalidStop => String::from("A line cannot stop there"),
+            EditError::TooFewStops => String::from("A line needs at least two stops"),
+            EditError::NotATerminus => String::from("Only open lines can be extended or shortened"),
+            EditError::RouteInUse => String::from("Trains are still running on this line"),
+            EditError::CarriageLimit => String::from("The train cannot take another carriage"),
+            EditError::NotAllowed => String::from("Not allowed in this mode"),
+            EditError::InvalidStation => String::from("No such station"),
+            EditError::Occupied => String::from("There is already something here"),
+            EditError::StationInUse => String::from("Lines still stop at this station"),
+            EditError::InvalidDepot => String::from("No such depot"),
+        }
+    }
+}
+
+pub struct Resources {
+    lines: u32,
+    trains: u32,
+    carriages: u32,
+}
+
+impl Resources {
+    pub fn new(lines: u32, trains: u32, carriages: u32) -> Self {
+        Resources {
+            lines,
+            trains,
+            carriages,
+        }
+    }
+
+    pub fn available(&self, resource: Resource) -> u32 {
+        match resource {
+            Resource::Line => self.lines,
+            Resource::Train => self.trains,
+            Resource::Carriage => self.carriages,
+        }
+    }
+
+    fn count_mut(&mut self, resource: Resource) -> &mut u32 {
+        match resource {
+            Resource::Line => &mut self.lines,
+            Resource::Train => &mut self.trains,
+            Resource::Carriage => &mut self.carriages,
+        }
+    }
+
+    pub fn spend(&mut self, resource: Resource) -> Result<(), EditError> {
+        let count = self.count_mut(resource);
+        if *count == 0 {
+            return Err(EditError::Exhausted(resource));
+        }
+        *count -= 1;
+        Ok(())
+    }
+
+    pub fn refund(&mut self, resource: Resource) {
+        *self.count_mut(resource) += 1;
+    }
+}
+
+pub struct Network<'a> {
+    pub ctx: &'a Context,
+    pub stations: &'a mut StationHandler,
+    pub routes: &'a mut RouteHandler,
+    pub vehicles: &'a mut VehicleHandler,
+    pub depots: &'a mut DepotHandler,
+    pub analytics: &'a mut Analytics,
+    pub resources: &'a mut Resources,
+    pub mode: GameMode,
+}
+
+impl Network<'_> {
+    fn spend(&mut self, resource: Resource) -> Result<(), EditError> {
+        if self.mode.has_unlimited_resources() {
+            return Ok(());
+        }
+        self.resources.spend(resource)
+    }
+
+    fn refund(&mut self, resource: Resource) {
+        if !self.mode.has_unlimited_resources() {
+            self.resources.refund(resource);
+        }
+    }
+
+    fn check_route_edit(&self) -> Result<(), EditError> {
+        if self.mode.allows_route_edits() {
+            Ok(())
+        } else {
+            Err(EditError::NotAllowed)
+        }
+    }
+
+    fn check_station_edit(&self, station: usize) -> Result<(), EditError> {
+        if !self.mode.allows_station_edits() {
+            return Err(EditError::NotAllowed);
+        }
+        if station >= self.stations.stations().len() {
+            return Err(EditError::InvalidStation);
+        }
+        Ok(())
+    }
+
+    // Moves a station while it is being dragged, before the move is committed to the history.
+    pub fn move_station(&mut self, station: usize, position: Vec2) -> Result<(), EditError> {
+        self.check_station_edit(station)?;
+        let (from, size) = {
+            let moved = self.stations.get(station);
+            (moved.position(), moved.size())
+        };
+        let overlaps = self.stations.stations().iter().any(|other| {
+            other.id() != station && other.position().distance(position) < other.size() + size
+        });
+        if overlaps || self.depots.depot_at(position).is_some() {
+            return Err(EditError::Occupied);
+        }
+        self.stations.move_station(station, position);
+        // Only lines that stop here or run past the old or new spot can change shape.
+        for route in self.routes.iter_mut() {
+            if route.serves(station)
+                || route.passes_near(from, size)
+                || route.passes_near(position, size)
+            {
+                route.mark_dirty();
+            }
+        }
+        Ok(())
+    }
+
+    fn is_occupied(&self, position: Vec2) -> bool {
+        self.stations.station_at(position).is_some() || self.depots.depot_at(position).is_some()
+    }
+
+    // Lines that stop at a changed station need new geometry, and every other line may now have
+    // to route around it.
+    fn regenerate_routes(&mut self) {
+        for route in self.routes.iter_mut() {
+            route.mark_dirty();
+        }
+    }
+
+    fn route(&self, route: usize) -> Result<&Route, EditError> {
+        self.routes.iter().nth(route).ok_or(EditError::InvalidRoute)
+    }
+
+    fn check_vehicle(&self, vehicle: usize) -> Result<(), EditError> {
+        if vehicle < self.vehicles.vehicles().len() {
+            Ok(())
+        } else {
+            Err(EditError::InvalidVehicle)
+        }
+    }
+
+    fn nearest_stop(&self, route: usize, position: Vec2) -> usize {
+        self.routes
+            .get(route)
+            .nearest_stop(self.stations, position)
+            .unwrap_or_default()
+    }
+
+    fn insert_stop(&mut self, route: usize, position: usize, stop: Stop) -> Result<(), EditError> {
+        self.check_route_edit()?;
+        let stops = self.route(route)?.stops();
+        let looped = self.route(route)?.is_looped();
+        let neighbour = |i: usize| stops.get(i).map(|stop| stop.index());
+        let previous = match position {
+            0 if looped => neighbour(stops.len() - 1),
+            0 => None,
+            _ => neighbour(position - 1),
+        };
+        let next = match neighbour(position) {
+            None if looped => neighbour(0),
+            next => next,
+        };
+        if position > stops.len()
+            || stop.index() >= self.stations.stations().len()
+            || previous == Some(stop.index())
+            || next == Some(stop.index())
+        {
+            return Err(EditError::InvalidStop);
+        }
+        self.routes.get_mut(route).insert_stop(position, stop);
+        Ok(())
+    }
+
+    fn remove_stop(&mut self, route: usize, position: usize) -> Result<Stop, EditError> {
+        self.check_route_edit()?;
+        let minimum = if self.route(route)?.is_looped() { 3 } else { 2 };
+        let stops = self.route(route)?.stops();
+        if position >= stops.len() {
+            return Err(EditError::InvalidStop);
+        }
+        if stops.len() <= minimum {
+            return Err(EditError::TooFewStops);
+        }
+        let stop = self.routes.get_mut(route).remove_stop(position);
+        Ok(stop)
+    }
+}
+
+// Drops everyone on board at a station; those who are already home are delivered.
+fn evacuate(stations: &mut StationHandler, vehicle: &mut dyn Vehicle, station: usize) {
+    let station = stations.get_mut(station);
+    for passenger in vehicle.take_passengers() {
+        if passenger.kind() == station.kind() {
+            station.receive_passenger(passenger, vehicle);
+        } else {
+            station.accept_walker(passenger, vehicle.position());
+        }
+    }
+}
+
+#[derive(Clone)]
+pub enum Command {
+    CreateRoute {
+        stops: Vec<Stop>,
+        is_looped: bool,
+    },
+    RemoveRoute,
+    ExtendRoute {
+        route: usize,
+        stop: Stop,
+        at_start: bool,
+    },
+    ShortenRoute {
+        route: usize,
+        at_start: bool,
+    },
+    InsertStop {
+        route: usize,
+        position: usize,
+        stop: Stop,
+    },
+    RemoveStop {
+        route: usize,
+        position: usize,
+    },
+    PlaceVehicle {
+        route: usize,
+        station: usize,
+    },
+    RemoveVehicle,
+    MoveVehicle {
+        vehicle: usize,
+        route: usize,
+        station: usize,
+    },
+    AddCarriage {
+        vehicle: usize,
+    },
+    RemoveCarriage {
+        vehicle: usize,
+    },
+    PlaceStation {
+        station: usize,
+        spec: StationSpec,
+    },
+    RemoveStation {
+        station: usize,
+    },
+    MoveStation {
+        station: usize,
+        from: Vec2,
+        to: Vec2,
+    },
+    SetSpawnRate {
+        station: usize,
+        spawn_rate: f32,
+    },
+    SetCapacity {
+        station: usize,
+        capacity: usize,
+    },
+    PlaceDepot {
+        depot: usize,
+        position: Vec2,
+    },
+    RemoveDepot {
+        depot: usize,
+    },
+}
+
+impl Command {
+    // Applies the command and returns the command that reverts it. RemoveRoute and RemoveVehicle
+    // only ever take the most recently added line or train, which is what undo needs.
+    pub fn apply(self, network: &mut Network) -> Result<Command, EditError> {
+        match self {
+            Command::CreateRoute { stops, is_looped } => {
+                if stops.len() < 2 {
+                    return Err(EditError::TooFewStops);
+                }
+                if stops
+                    .iter()
+                    .any(|stop| stop.index() >= network.stations.stations().len())
+                {
+                    return Err(EditError::InvalidStop);
+                }
+                network.spend(Resource::Line)?;
+                network.routes.add_route(stops, is_looped);
+                Ok(Command::RemoveRoute)
+            }
+            Command::RemoveRoute => {
+                network.check_route_edit()?;
+                let route = network
+                    .routes
+                    .iter()
+                    .len()
+                    .checked_sub(1)
+                    .ok_or(EditError::InvalidRoute)?;
+                if !network.vehicles.metros_on_route(route).is_empty() {
+                    return Err(EditError::RouteInUse);
+                }
+                let removed = network.routes.remove_last_route().unwrap();
+                network.refund(Resource::Line);
+                Ok(Command::CreateRoute {
+                    stops: removed.stops().clone(),
+                    is_looped: removed.is_looped(),
+                })
+            }
+            Command::ExtendRoute {
+                route,
+                stop,
+                at_start,
+            } => {
+                if network.route(route)?.is_looped() {
+                    return Err(EditError::NotATerminus);
+                }
+                let position = if at_start {
+                    0
+                } else {
+                    network.route(route)?.stops().len()
+                };
+                network.insert_stop(route, position, stop)?;
+                Ok(Command::ShortenRoute { route, at_start })
+            }
+            Command::ShortenRoute { route, at_start } => {
+                if network.route(route)?.is_looped() {
+                    return Err(EditError::NotATerminus);
+                }
+                let position = if at_start {
+                    0
+                } else {
+                    network.route(route)?.stops().len() - 1
+                };
+                let stop = network.remove_stop(route, position)?;
+                Ok(Command::ExtendRoute {
+                    route,
+                    stop,
+                    at_start,
+                })
+            }
+            Command::InsertStop {
+                route,
+                position,
+                stop,
+            } => {
+                network.insert_stop(route, position, stop)?;
+                Ok(Command::RemoveStop { route, position })
+            }
+            Command::RemoveStop { route, position } => {
+                let stop = network.remove_stop(route, position)?;
+                Ok(Command::InsertStop {
+                    route,
+                    position,
+                    stop,
+                })
+            }
+            Command::PlaceVehicle { route, station } => {
+                if !network
+                    .route(route)?
+                    .stops()
+                    .iter()
+                    .any(|stop| stop.index() == station)
+                {
+                    return Err(EditError::InvalidStop);
+                }
+                network.spend(Resource::Train)?;
+                let mut metro = Metro::new(network.ctx, route);
+                metro.set_position(network.stations.get(station).position());
+                metro.relocate(station);
+                if !network.vehicles.add_vehicle(Box::new(metro)) {
+                    network.refund(Resource::Train);
+                    return Err(EditError::Exhausted(Resource::Train));
+                }
+                Ok(Command::RemoveVehicle)
+            }
+            Command::RemoveVehicle => {
+                let mut vehicle = network
+                    .vehicles
+                    .remove_last_vehicle()
+                    .ok_or(EditError::InvalidVehicle)?;
+                let route = vehicle.route();
+                let station = network.nearest_stop(route, vehicle.position());
+                evacuate(network.stations, vehicle.as_mut(), station);
+                network.analytics.forget_vehicle(vehicle.id());
+                network.refund(Resource::Train);
+                for _ in 1..vehicle.carriages() {
+                    network.refund(Resource::Carriage);
+                }
+                Ok(Command::PlaceVehicle { route, station })
+            }
+            Command::MoveVehicle {
+                vehicle,
+                route,
+                station,
+            } => {
+                if !network.mode.allows_vehicle_moves() {
+                    return Err(EditError::NotAllowed);
+                }
+                network.check_vehicle(vehicle)?;
+                if !network
+                    .route(route)?
+                    .stops()
+                    .iter()
+                    .any(|stop| stop.index() == station)
+                {
+                    return Err(EditError::InvalidStop);
+                }
+                let from_route = network.vehicles.get(vehicle).route();
+                let from_station =
+                    network.nearest_stop(from_route, network.vehicles.get(vehicle).position());
+                evacuate(
+                    network.stations,
+                    network.vehicles.get_mut(vehicle).as_mut(),
+                    from_station,
+                );
+                network.vehicles.set_route(vehicle, route);
+                network.vehicles.get_mut(vehicle).relocate(station);
+                Ok(Command::MoveVehicle {
+                    vehicle,
+                    route: from_route,
+                    station: from_station,
+                })
+            }
+            Command::AddCarriage { vehicle } => {
+                network.check_vehicle(vehicle)?;
+                let carriages = network.vehicles.get(vehicle).carriages();
+                if carriages >= MAX_CARRIAGES {
+                    return Err(EditError::CarriageLimit);
+                }
+                network.spend(Resource::Carriage)?;
+                network
+                    .vehicles
+                    .get_mut(vehicle)
+                    .set_carriages(carriages + 1);
+                Ok(Command::RemoveCarriage { vehicle })
+            }
+            Command::RemoveCarriage { vehicle } => {
+                network.check_vehicle(vehicle)?;
+                let carriages = network.vehicles.get(vehicle).carriages();
+                if carriages <= 1 {
+                    return Err(EditError::CarriageLimit);
+                }
+                network
+                    .vehicles
+                    .get_mut(vehicle)
+                    .set_carriages(carriages - 1);
+                network.refund(Resource::Carriage);
+                Ok(Command::AddCarriage { vehicle })
+            }
+            Command::PlaceStation { station, spec } => {
+                if !network.mode.allows_station_edits() {
+                    return Err(EditError::NotAllowed);
+                }
+                if station > network.stations.stations().len() || spec.spawn_rate <= 0.0 {
+                    return Err(EditError::InvalidStation);
+                }
+                if network.is_occupied(spec.position) {
+                    return Err(EditError::Occupied);
+                }
+                network.stations.insert_station(station, spec);
+                network
+                    .routes
+                    .renumber_stations(|s| if s >= station { s + 1 } else { s });
+                network
+                    .vehicles
+                    .renumber_stations(|s| if s >= station { s + 1 } else { s });
+                network
+                    .analytics
+                    .renumber_stations(|s| Some(if s >= station { s + 1 } else { s }));
+                network.regenerate_routes();
+                Ok(Command::RemoveStation { station })
+            }
+            Command::RemoveStation { station } => {
+                network.check_station_edit(station)?;
+                if network.routes.iter().any(|route| route.serves(station)) {
+                    return Err(EditError::StationInUse);
+                }
+                let spec = network.stations.remove_station(station);
+                network
+                    .routes
+                    .renumber_stations(|s| if s > station { s - 1 } else { s });
+                network
+                    .vehicles
+                    .renumber_stations(|s| if s > station { s - 1 } else { s });
+                network
+                    .analytics
+                    .renumber_stations(|s| match s.cmp(&station) {
+                        Ordering::Less => Some(s),
+                        Ordering::Equal => None,
+                        Ordering::Greater => Some(s - 1),
+                    });
+                network.regenerate_routes();
+                Ok(Command::PlaceStation { station, spec })
+            }
+            Command::MoveStation { station, from, to } => {
+                network.move_station(station, to)?;
+                Ok(Command::MoveStation {
+                    station,
+                    from: to,
+                    to: from,
+                })
+            }
+            Command::SetSpawnRate {
+                station,
+                spawn_rate,
+            } => {
+                network.check_station_edit(station)?;
+                if spawn_rate <= 0.0 {
+                    return Err(EditError::InvalidStation);
+                }
+                let previous = network.stations.get(station).spawn_rate();
+                network.stations.get_mut(station).set_spawn_rate(spawn_rate);
+                Ok(Command::SetSpawnRate {
+                    station,
+                    spawn_rate: previous,
+                })
+            }
+            Command::SetCapacity { station, capacity } => {
+                network.check_station_edit(station)?;
+                let previous = network.stations.get(station).capacity();
+                network.stations.get_mut(station).set_capacity(capacity);
+                Ok(Command::SetCapacity {
+                    station,
+                    capacity: previous,
+                })
+            }
+            Command::PlaceDepot { depot, position } => {
+                if !network.mode.allows_station_edits() {
+                    return Err(EditError::NotAllowed);
+                }
+                if depot > network.depots.depots().len() {
+                    return Err(EditError::InvalidDepot);
+                }
+                if network.is_occupied(position) {
+                    return Err(EditError::Occupied);
+                }
+                network.depots.insert_depot(depot, position);
+                Ok(Command::RemoveDepot { depot })
+            }
+            Command::RemoveDepot { depot } => {
+                if !network.mode.allows_station_edits() {
+                    return Err(EditError::NotAllowed);
+                }
+                if depot >= network.depots.depots().len() {
+                    return Err(EditError::InvalidDepot);
+                }
+                let position = network.depots.remove_depot(depot);
+                Ok(Command::PlaceDepot { depot, position })
+            }
+        }
+    }
+}
@@ -0,0 +1,47 @@
+#[derive(strum_macros::EnumIter, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GameMode {
+    Classic,
+    Endless,
+    Extreme,
+    Creative,
+}
+
+impl GameMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            GameMode::Classic => "Classic",
+            GameMode::Endless => "Endless",
+            GameMode::Extreme => "Extreme",
+            GameMode::Creative => "Creative",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            GameMode::Classic => "Keep stations from overcrowding for as long as you can",
+            GameMode::Endless => "No game over and no score, just build",
+            GameMode::Extreme => "Lines are permanent once placed and trains cannot be moved",
+            GameMode::Creative => "Place stations freely with unlimited resources",
+        }
+    }
+
+    pub fn has_unlimited_resources(&self) -> bool {
+        *self == GameMode::Creative
+    }
+
+    pub fn allows_station_edits(&self) -> bool {
+        *self == GameMode::Creative
+    }
+
+    pub fn allows_route_edits(&self) -> bool {
+        *self != GameMode::Extreme
+    }
+
+    pub fn allows_vehicle_moves(&self) -> bool {
+        *self != GameMode::Extreme
+    }
+
+    pub fn has_game_over(&self) -> bool {
+        matches!(self, GameMode::Classic | GameMode::Extreme)
+    }
+}
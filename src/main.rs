@@ -1,36 +1,60 @@
 use ggez::{
     ContextBuilder,
-    conf::{NumSamples, WindowMode, WindowSetup},
+    conf::{WindowMode, WindowSetup},
     event::run,
 };
+use utils::{
+    colors::Colors,
+    config::{MAX_WINDOW_SIZE, MIN_WINDOW_SIZE, Settings},
+};
 use world::MetroWorld;
 
+mod analytics;
+mod depot;
+mod edit;
 mod passenger;
 mod route;
+mod scene;
 mod shape;
 mod station;
+mod ui;
 mod vehicle;
 mod utils;
 mod world;
 
+fn arg_value<'a>(args: &'a [String], flag: &str) -> Option<&'a String> {
+    args.iter().skip_while(|arg| *arg != flag).nth(1)
+}
+
 fn main() {
+    let settings = Settings::load();
     let (mut ctx, event_loop) = ContextBuilder::new("rusty-metro", "waltsai")
         .window_mode(
             WindowMode::default()
-                .dimensions(1280.0, 720.0)
+                .dimensions(settings.window_width, settings.window_height)
                 .resizable(true)
-                .min_dimensions(640.0, 360.0)
-                .max_dimensions(1920.0, 1080.0),
+                .min_dimensions(MIN_WINDOW_SIZE.0, MIN_WINDOW_SIZE.1)
+                .max_dimensions(MAX_WINDOW_SIZE.0, MAX_WINDOW_SIZE.1),
         )
         .window_setup(
             WindowSetup::default()
                 .title("Rusty Metro")
                 .icon("/icon.png")
-                .samples(NumSamples::Four),
+                .samples(settings.samples),
         )
         .build()
         .expect("Failed to create ggez context!");
 
-    let game = MetroWorld::new(&mut ctx, 41);
+    Colors::set_theme(settings.theme.load());
+    let mut game = MetroWorld::new(&mut ctx, 41, settings);
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(path) = arg_value(&args, "--od")
+        && let Err(e) = game.load_od_matrix(path)
+    {
+        eprintln!("Failed to load OD matrix, using gravity model: {e}");
+    }
+    if let Some(dir) = arg_value(&args, "--export") {
+        game.set_export_dir(dir);
+    }
     run(ctx, event_loop, game);
 }
@@ -2,29 +2,69 @@ use ggez::glam::Vec2;
 
 use crate::station::types::StationShape;
 
+pub mod routing;
+pub mod satisfaction;
+
+pub const PASSENGER_PATIENCE: f32 = 45.0;
+pub const RIDING_PATIENCE_RATE: f32 = 0.25;
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum PassengerState {
     OnStation,
     OnVehicle,
     LeavingStation(usize, Vec2),
-    LeavingVehicle(usize, Vec2)
+    LeavingVehicle(usize, Vec2),
+    Abandoning(Vec2),
+    Walking(usize, Vec2),
 }
 
 #[derive(Debug, Clone, Copy)]
 pub struct Passenger {
     kind: StationShape,
-    state: PassengerState
+    state: PassengerState,
+    wait_time: f32,
+    trip_time: f32,
+    patience: f32,
 }
 
 impl Passenger {
     pub fn new(kind: StationShape) -> Self {
-        Passenger { kind, state: PassengerState::OnStation }
+        Passenger {
+            kind,
+            state: PassengerState::OnStation,
+            wait_time: 0.0,
+            trip_time: 0.0,
+            patience: PASSENGER_PATIENCE,
+        }
     }
 
     pub fn kind(&self) -> StationShape {
         self.kind
     }
 
+    pub fn wait_time(&self) -> f32 {
+        self.wait_time
+    }
+
+    pub fn trip_time(&self) -> f32 {
+        self.trip_time
+    }
+
+    pub fn wait(&mut self, delta: f32) {
+        self.wait_time += delta;
+        self.trip_time += delta;
+        self.patience -= delta;
+    }
+
+    pub fn ride(&mut self, delta: f32) {
+        self.trip_time += delta;
+        self.patience -= delta * RIDING_PATIENCE_RATE;
+    }
+
+    pub fn is_out_of_patience(&self) -> bool {
+        self.patience <= 0.0
+    }
+
     pub fn state(&self) -> PassengerState {
         self.state
     }
@@ -0,0 +1,108 @@
+use strum::{EnumCount, IntoEnumIterator};
+
+use crate::{
+    route::handler::RouteHandler,
+    station::{handler::StationHandler, types::StationShape, walking::WalkingLinks},
+};
+
+pub const RIDING_SPEED: f32 = 150.0;
+pub const STOP_PENALTY: f32 = 1.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NextHop {
+    Arrived,
+    Ride,
+    Walk(usize),
+}
+
+pub struct RoutingTable {
+    hops: Vec<[Option<NextHop>; StationShape::COUNT]>,
+    revision: Option<(u32, u32)>,
+}
+
+impl RoutingTable {
+    pub fn new() -> Self {
+        RoutingTable {
+            hops: vec![],
+            revision: None,
+        }
+    }
+
+    // Rebuilds only when stations, walking links or routes changed since the last build.
+    // Walking links depend on the lines, so they are refreshed first when routes changed.
+    pub fn refresh(&mut self, stations: &mut StationHandler, routes: &RouteHandler) {
+        if self.revision.map(|(_, routes)| routes) != Some(routes.revision()) {
+            stations.set_lines(routes.lines());
+        }
+        if self.revision != Some((stations.revision(), routes.revision())) {
+            *self = Self::build(stations, routes);
+        }
+    }
+
+    pub fn build(stations: &StationHandler, routes: &RouteHandler) -> Self {
+        let station_count = stations.stations().len();
+        let mut edges: Vec<Vec<(usize, f32, bool)>> = vec![vec![]; station_count];
+        for route in routes.iter() {
+            let stops = route.stops();
+            let pairs = if route.is_looped() {
+                stops.len()
+            } else {
+                stops.len().saturating_sub(1)
+            };
+            for idx in 0..pairs {
+                let a = stops[idx].index();
+                let b = stops[(idx + 1) % stops.len()].index();
+                let cost = stations
+                    .get(a)
+                    .position()
+                    .distance(stations.get(b).position())
+                    / RIDING_SPEED
+                    + STOP_PENALTY;
+                edges[a].push((b, cost, false));
+                edges[b].push((a, cost, false));
+            }
+        }
+        for &(a, b, distance) in stations.walking_links().links() {
+            let cost = WalkingLinks::walking_time(distance);
+            edges[a].push((b, cost, true));
+            edges[b].push((a, cost, true));
+        }
+
+        let mut hops = vec![[None; StationShape::COUNT]; station_count];
+        for shape in StationShape::iter() {
+            let mut cost = vec![f32::INFINITY; station_count];
+            let mut done = vec![false; station_count];
+            for station in stations.stations().iter().filter(|s| s.kind() == shape) {
+                cost[station.id()] = 0.0;
+                hops[station.id()][shape as usize] = Some(NextHop::Arrived);
+            }
+            while let Some(v) = (0..station_count)
+                .filter(|&i| !done[i] && cost[i].is_finite())
+                .min_by(|&a, &b| cost[a].total_cmp(&cost[b]))
+            {
+                done[v] = true;
+                for &(u, edge_cost, is_walk) in edges[v].iter() {
+                    if cost[v] + edge_cost >= cost[u] {
+                        continue;
+                    }
+                    cost[u] = cost[v] + edge_cost;
+                    hops[u][shape as usize] = Some(if is_walk {
+                        NextHop::Walk(v)
+                    } else {
+                        NextHop::Ride
+                    });
+                }
+            }
+        }
+        RoutingTable {
+            hops,
+            revision: Some((stations.revision(), routes.revision())),
+        }
+    }
+
+    pub fn next_hop(&self, station: usize, destination: StationShape) -> Option<NextHop> {
+        self.hops
+            .get(station)
+            .and_then(|hops| hops[destination as usize])
+    }
+}
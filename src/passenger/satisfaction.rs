@@ -0,0 +1,34 @@
+pub const SATISFACTION_PRIOR: f32 = 10.0;
+pub const HARD_MODE_MIN_SATISFACTION: f32 = 0.5;
+
+pub struct Satisfaction {
+    delivered: u32,
+    abandoned: u32,
+}
+
+impl Satisfaction {
+    pub fn new() -> Self {
+        Satisfaction {
+            delivered: 0,
+            abandoned: 0,
+        }
+    }
+
+    pub fn delivered(&self) -> u32 {
+        self.delivered
+    }
+
+    pub fn abandoned(&self) -> u32 {
+        self.abandoned
+    }
+
+    pub fn update(&mut self, delivered: u32, abandoned: u32) {
+        self.delivered = delivered;
+        self.abandoned = abandoned;
+    }
+
+    pub fn score(&self) -> f32 {
+        (self.delivered as f32 + SATISFACTION_PRIOR)
+            / ((self.delivered + self.abandoned) as f32 + SATISFACTION_PRIOR)
+    }
+}
@@ -0,0 +1,57 @@
+use std::collections::VecDeque;
+
+use super::segment::Segment;
+
+pub const FLOW_WINDOW: f32 = 120.0;
+
+pub struct SegmentFlow {
+    clock: f32,
+    path: Vec<Segment>,
+    traversals: Vec<VecDeque<(f32, usize)>>,
+}
+
+impl SegmentFlow {
+    pub fn new() -> Self {
+        SegmentFlow {
+            clock: 0.0,
+            path: vec![],
+            traversals: vec![],
+        }
+    }
+
+    // Traversals are per segment, so they only survive a rebuild that kept the same path.
+    pub fn follow(&mut self, path: &[Segment]) {
+        if self.path.len() == path.len()
+            && self.path.iter().zip(path).all(|(a, b)| a.same_geometry(b))
+        {
+            return;
+        }
+        self.path = path.to_vec();
+        self.traversals = vec![VecDeque::new(); path.len()];
+    }
+
+    pub fn update(&mut self, delta: f32) {
+        self.clock += delta;
+        for traversals in self.traversals.iter_mut() {
+            while traversals
+                .front()
+                .is_some_and(|&(time, _)| self.clock - time > FLOW_WINDOW)
+            {
+                traversals.pop_front();
+            }
+        }
+    }
+
+    pub fn record(&mut self, segment: usize, passengers: usize) {
+        if let Some(traversals) = self.traversals.get_mut(segment) {
+            traversals.push_back((self.clock, passengers));
+        }
+    }
+
+    pub fn flow(&self, segment: usize) -> usize {
+        self.traversals
+            .get(segment)
+            .map(|traversals| traversals.iter().map(|&(_, passengers)| passengers).sum())
+            .unwrap_or_default()
+    }
+}
@@ -1,22 +1,38 @@
+use std::collections::HashMap;
+
 use ggez::{
     Context,
+    glam::Vec2,
     graphics::{Canvas, Color},
 };
 
 use crate::{station::handler::StationHandler, utils::colors::Colors};
 
-use super::{Route, stop::Stop};
+use super::{
+    LineMarking, LineStyle, PLATFORM_GAP_WIDTH, ROUTE_LINE_WIDTH, Route,
+    platform::PlatformAllocator, stop::Stop,
+};
 
 pub struct RouteHandler {
     routes: Vec<Route>,
+    platforms: PlatformAllocator,
+    line_style: LineStyle,
+    marking: LineMarking,
+    show_labels: bool,
     palette: Vec<Color>,
+    revision: u32,
 }
 
 impl RouteHandler {
     pub fn new() -> Self {
         RouteHandler {
             routes: vec![],
-            palette: Colors::default_palette()
+            platforms: PlatformAllocator::new(),
+            line_style: LineStyle::Octilinear,
+            marking: LineMarking::Colour,
+            show_labels: false,
+            palette: Colors::line_palette(),
+            revision: 0,
         }
     }
 
@@ -24,19 +40,163 @@ impl RouteHandler {
         &self.routes[index]
     }
 
+    pub fn get_mut(&mut self, index: usize) -> &mut Route {
+        &mut self.routes[index]
+    }
+
     pub fn add_route(&mut self, stops: Vec<Stop>, is_looped: bool) {
         self.routes.push(Route::new(
             self.routes.len(),
             stops,
-            self.palette[self.routes.len()],
+            self.palette[self.routes.len() % self.palette.len()],
             is_looped,
         ));
+        let route = self.routes.last_mut().unwrap();
+        route.set_line_style(self.line_style);
+        route.set_marking(self.marking);
+        route.set_label_visible(self.show_labels);
+    }
+
+    pub fn remove_last_route(&mut self) -> Option<Route> {
+        let route = self.routes.pop()?;
+        for route in self.routes.iter_mut() {
+            route.mark_dirty();
+        }
+        self.revision = self.revision.wrapping_add(1);
+        Some(route)
+    }
+
+    // The stations served by each route.
+    pub fn lines(&self) -> Vec<Vec<usize>> {
+        self.routes
+            .iter()
+            .map(|route| route.stops().iter().map(|stop| stop.index()).collect())
+            .collect()
+    }
+
+    // Bumped whenever a route is rebuilt or removed.
+    pub fn revision(&self) -> u32 {
+        self.revision
+    }
+
+    pub fn renumber_stations(&mut self, renumber: impl Fn(usize) -> usize) {
+        for route in self.routes.iter_mut() {
+            route.renumber_stops(&renumber);
+        }
+    }
+
+    pub fn set_palette(&mut self, palette: Vec<Color>) {
+        self.palette = palette;
+        for route in self.routes.iter_mut() {
+            route.set_color(self.palette[route.id() % self.palette.len()]);
+        }
+    }
+
+    pub fn set_marking(&mut self, marking: LineMarking) {
+        self.marking = marking;
+        for route in self.routes.iter_mut() {
+            route.set_marking(marking);
+        }
+    }
+
+    pub fn set_labels_visible(&mut self, show_labels: bool) {
+        self.show_labels = show_labels;
+        for route in self.routes.iter_mut() {
+            route.set_label_visible(show_labels);
+        }
+    }
+
+    pub fn line_style(&self) -> LineStyle {
+        self.line_style
+    }
+
+    pub fn set_line_style(&mut self, line_style: LineStyle) {
+        self.line_style = line_style;
+        for route in self.routes.iter_mut() {
+            route.set_line_style(line_style);
+        }
     }
 
     pub fn update(&mut self, ctx: &Context, stations: &StationHandler, delta: f32) {
-        let mut routes_on_station = vec![1; stations.stations().len()];
+        let changed: Vec<usize> = (0..self.routes.len())
+            .filter(|&i| self.routes[i].is_dirty())
+            .collect();
+        if !changed.is_empty() {
+            self.revision = self.revision.wrapping_add(1);
+            if self.platforms.allocate(&self.routes) {
+                for route in self.routes.iter_mut() {
+                    route.mark_dirty();
+                }
+            }
+            // Only lines that meet a changed line can have their best stop sides moved by it.
+            for i in 0..self.routes.len() {
+                if self.routes[i].has_auto_stops()
+                    && changed
+                        .iter()
+                        .any(|&c| self.routes[c].shares_station(&self.routes[i]))
+                {
+                    self.routes[i].mark_dirty();
+                }
+            }
+            self.layout_stops(stations);
+            // Lane order follows the stop sides, so it can only be settled once they are.
+            self.update_lanes();
+        }
         for route in self.routes.iter_mut() {
-            route.update(ctx, &stations, &mut routes_on_station, delta);
+            route.update(ctx, stations, &self.platforms, delta);
+        }
+    }
+
+    // Every dirty path is brought up to date first so each layout sees the others as they
+    // will be drawn.
+    fn layout_stops(&mut self, stations: &StationHandler) {
+        for route in self.routes.iter_mut().filter(|route| route.is_dirty()) {
+            route.update_path(stations, &self.platforms);
+        }
+        for i in 0..self.routes.len() {
+            if !self.routes[i].is_dirty() || !self.routes[i].has_auto_stops() {
+                continue;
+            }
+            let others: Vec<(Vec2, Vec2)> = self
+                .routes
+                .iter()
+                .filter(|route| route.id() != i)
+                .flat_map(|route| route.straight_segments())
+                .collect();
+            self.routes[i].layout_stops(stations, &self.platforms, &others);
+        }
+    }
+
+    fn update_lanes(&mut self) {
+        let mut corridors: HashMap<(usize, usize), Vec<(f32, usize)>> = HashMap::new();
+        for route in self.routes.iter() {
+            for (curr, next) in route.legs() {
+                let (key, order) = if curr.index() < next.index() {
+                    ((curr.index(), next.index()), curr.side_factor())
+                } else {
+                    ((next.index(), curr.index()), -next.side_factor())
+                };
+                let corridor = corridors.entry(key).or_default();
+                if !corridor.iter().any(|&(_, id)| id == route.id()) {
+                    corridor.push((order, route.id()));
+                }
+            }
+        }
+
+        let mut lanes: Vec<HashMap<(usize, usize), f32>> = vec![HashMap::new(); self.routes.len()];
+        for (key, mut corridor) in corridors {
+            if corridor.len() < 2 {
+                continue;
+            }
+            corridor.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+            let center = (corridor.len() - 1) as f32 / 2.0;
+            for (slot, &(_, id)) in corridor.iter().enumerate() {
+                let offset = (slot as f32 - center) * (ROUTE_LINE_WIDTH + PLATFORM_GAP_WIDTH);
+                lanes[id].insert(key, offset);
+            }
+        }
+        for (route, lanes) in self.routes.iter_mut().zip(lanes) {
+            route.set_lanes(lanes);
         }
     }
 
@@ -1,29 +1,107 @@
 use std::{
+    collections::HashMap,
     f32::consts::{PI, SQRT_2},
-    mem::swap,
+    iter::once,
+    mem::{swap, take},
 };
 
 use ggez::{
     Context,
     glam::Vec2,
-    graphics::{Canvas, Color, DrawMode, DrawParam, Mesh, MeshBuilder},
+    graphics::{Canvas, Color, DrawMode, DrawParam, Mesh, MeshBuilder, Text},
 };
+use flow::SegmentFlow;
+use pathfinding::{GRID_STEP, Obstacle, is_blocked, plan_path};
+use platform::PlatformAllocator;
 use segment::{Segment, VehicleState};
-use stop::Stop;
+use stop::{Stop, StopSide};
+use timetable::Timetable;
 
 use crate::{
-    station::{Station, handler::StationHandler},
-    utils::{AngleCalc, AngleNormalizer, lerp_angle},
+    station::{Station, handler::StationHandler, types::StationShape},
+    utils::{AngleCalc, AngleNormalizer, colors::Colors, lerp_angle, segments_cross},
 };
 
 pub const ROUTE_LINE_WIDTH: f32 = 4.5;
 pub const PLATFORM_LINE_WIDTH: f32 = 3.0;
 pub const PLATFORM_GAP_WIDTH: f32 = 0.5;
 pub const PLATFORM_SMOOTHNESS: f32 = 50.0;
+pub const CROSSING_PENALTY: f32 = 200.0;
+pub const SIDE_ASSIGNMENT_PASSES: usize = 2;
+pub const PROJECTION_SAMPLES: usize = 8;
+pub const CURVE_SMOOTHNESS: usize = 20;
+// One pattern per slot of the seven-colour line palettes.
+pub const DASH_PATTERNS: [&[f32]; 7] = [
+    &[],
+    &[14.0, 7.0],
+    &[5.0, 5.0],
+    &[14.0, 5.0, 4.0, 5.0],
+    &[24.0, 6.0],
+    &[3.0, 8.0],
+    &[14.0, 4.0, 4.0, 4.0, 4.0, 4.0],
+];
+pub const GLYPH_SCALE: f32 = 1.6;
+pub const GLYPH_COUNT: usize = 5;
+pub const LABEL_SIZE: f32 = 14.0;
+pub const LABEL_OFFSET: f32 = 16.0;
 
+// Bends of each leg, keyed by its two stops and the side each is served from.
+type LegPaths = HashMap<(usize, StopSide, usize, StopSide), Vec<Vec2>>;
+
+#[derive(strum_macros::EnumIter, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LineStyle {
+    Octilinear,
+    Curved,
+}
+
+impl LineStyle {
+    pub fn next(&self) -> Self {
+        match self {
+            LineStyle::Octilinear => LineStyle::Curved,
+            LineStyle::Curved => LineStyle::Octilinear,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            LineStyle::Octilinear => "octilinear",
+            LineStyle::Curved => "curved",
+        }
+    }
+}
+
+#[derive(strum_macros::EnumIter, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LineMarking {
+    Colour,
+    Dashes,
+    Glyphs,
+}
+
+impl LineMarking {
+    pub fn next(&self) -> Self {
+        match self {
+            LineMarking::Colour => LineMarking::Dashes,
+            LineMarking::Dashes => LineMarking::Glyphs,
+            LineMarking::Glyphs => LineMarking::Colour,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            LineMarking::Colour => "colour",
+            LineMarking::Dashes => "dashes",
+            LineMarking::Glyphs => "glyphs",
+        }
+    }
+}
+
+pub mod flow;
 pub mod handler;
+pub mod pathfinding;
+pub mod platform;
 pub mod segment;
 pub mod stop;
+pub mod timetable;
 
 pub struct Route {
     id: usize,
@@ -33,6 +111,17 @@ pub struct Route {
     is_looped: bool,
     mesh: Option<Mesh>,
     dirty: bool,
+    // Only the look changed, so the mesh is redrawn over the existing path.
+    mesh_dirty: bool,
+
+    offsets: Vec<f32>,
+    calls: Vec<(usize, f32)>,
+    timetable: Timetable,
+    flow: SegmentFlow,
+    lanes: HashMap<(usize, usize), f32>,
+    line_style: LineStyle,
+    marking: LineMarking,
+    show_label: bool,
 }
 
 impl Route {
@@ -45,6 +134,15 @@ impl Route {
             is_looped,
             mesh: None,
             dirty: true,
+            mesh_dirty: false,
+            offsets: vec![],
+            calls: vec![],
+            timetable: Timetable::new(),
+            flow: SegmentFlow::new(),
+            lanes: HashMap::new(),
+            line_style: LineStyle::Octilinear,
+            marking: LineMarking::Colour,
+            show_label: false,
         }
     }
 
@@ -60,10 +158,224 @@ impl Route {
         self.color
     }
 
+    pub fn set_color(&mut self, color: Color) {
+        self.color = color;
+        self.mesh_dirty = true;
+    }
+
     pub fn is_looped(&self) -> bool {
         self.is_looped
     }
 
+    // The shapes a train leaving `from` can still reach, in the order it reaches them: the stops
+    // ahead first, then, on an open line, the ones behind it once it turns at the terminus.
+    pub fn served_shapes(
+        &self,
+        stations: &StationHandler,
+        from: usize,
+        direction: f32,
+    ) -> Vec<StationShape> {
+        let mut order: Vec<&Stop> = self.stops.iter().collect();
+        if direction < 0.0 {
+            order.reverse();
+        }
+        let start = order.iter().position(|stop| stop.index() == from).unwrap_or(0);
+        let (behind, ahead) = order.split_at(start);
+        let ahead = ahead.iter().skip(1);
+        let stops: Vec<&&Stop> = if self.is_looped {
+            ahead.chain(behind.iter()).collect()
+        } else {
+            ahead.chain(behind.iter().rev()).collect()
+        };
+        let mut shapes = vec![];
+        for stop in stops.into_iter().filter(|stop| stop.index() != from) {
+            let kind = stations.get(stop.index()).kind();
+            if !shapes.contains(&kind) {
+                shapes.push(kind);
+            }
+        }
+        shapes
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    pub fn mark_dirty(&mut self) {
+        self.dirty = true;
+    }
+
+    pub fn has_auto_stops(&self) -> bool {
+        self.stops.iter().any(|stop| !stop.is_manual())
+    }
+
+    pub fn insert_stop(&mut self, position: usize, stop: Stop) {
+        self.stops.insert(position, stop);
+        self.dirty = true;
+    }
+
+    pub fn remove_stop(&mut self, position: usize) -> Stop {
+        self.dirty = true;
+        self.stops.remove(position)
+    }
+
+    pub fn serves(&self, station: usize) -> bool {
+        self.stops.iter().any(|stop| stop.index() == station)
+    }
+
+    // Any shared corridor also shares its two stations.
+    pub fn shares_station(&self, other: &Route) -> bool {
+        self.stops.iter().any(|stop| other.serves(stop.index()))
+    }
+
+    pub fn renumber_stops(&mut self, renumber: impl Fn(usize) -> usize) {
+        for stop in self.stops.iter_mut() {
+            let index = renumber(stop.index());
+            if index != stop.index() {
+                stop.set_index(index);
+                self.dirty = true;
+            }
+        }
+        // Keeps the rebuilt calls matching, so timetable adherence survives the renumbering.
+        for call in self.calls.iter_mut() {
+            call.0 = renumber(call.0);
+        }
+    }
+
+    pub fn nearest_stop(&self, stations: &StationHandler, position: Vec2) -> Option<usize> {
+        self.stops
+            .iter()
+            .map(|stop| stop.index())
+            .min_by(|&a, &b| {
+                let a = stations.get(a).position().distance(position);
+                let b = stations.get(b).position().distance(position);
+                a.total_cmp(&b)
+            })
+    }
+
+    // Some(true) if the station is the first stop of an open line, Some(false) if it is the last.
+    pub fn terminus(&self, station: usize) -> Option<bool> {
+        if self.is_looped {
+            return None;
+        }
+        if self.stops.first().map(|stop| stop.index()) == Some(station) {
+            Some(true)
+        } else if self.stops.last().map(|stop| stop.index()) == Some(station) {
+            Some(false)
+        } else {
+            None
+        }
+    }
+
+    pub fn platform_segment(&self, station: usize) -> Option<(usize, f32)> {
+        self.path_nodes
+            .iter()
+            .enumerate()
+            .find_map(|(i, node)| match node.state() {
+                VehicleState::LastPlatform(direction) if node.station() == station => {
+                    Some((i, if direction < 0.0 { 0.0 } else { node.length() }))
+                }
+                VehicleState::ArrivePlatform(..) if node.station() == station => {
+                    Some((i, node.length()))
+                }
+                _ => None,
+            })
+    }
+
+    // Where a train travelling in `direction` halts at the station's platform.
+    pub fn platform_stop(&self, station: usize, direction: f32) -> Option<(usize, f32)> {
+        self.path_nodes
+            .iter()
+            .enumerate()
+            .find_map(|(i, node)| match node.state() {
+                _ if node.station() != station => None,
+                VehicleState::LastPlatform(end) => {
+                    Some((i, if end < 0.0 { 0.0 } else { node.length() }))
+                }
+                VehicleState::ArrivePlatform(..) if direction > 0.0 => Some((i, node.length())),
+                VehicleState::LeavePlatform(..) if direction < 0.0 => Some((i, 0.0)),
+                _ => None,
+            })
+    }
+
+    // The point of the path closest to `position`, as a segment and distance along it.
+    pub fn project(&self, position: Vec2) -> Option<(usize, f32)> {
+        self.path_nodes
+            .iter()
+            .enumerate()
+            .flat_map(|(i, node)| {
+                (0..=PROJECTION_SAMPLES)
+                    .map(move |k| (i, node.length() * k as f32 / PROJECTION_SAMPLES as f32))
+            })
+            .min_by(|&(a, da), &(b, db)| {
+                self.calculate_position(a, da)
+                    .distance(position)
+                    .total_cmp(&self.calculate_position(b, db).distance(position))
+            })
+    }
+
+    pub fn legs(&self) -> Vec<(Stop, Stop)> {
+        let count = if self.is_looped {
+            self.stops.len()
+        } else {
+            self.stops.len().saturating_sub(1)
+        };
+        (0..count)
+            .map(|i| (self.stops[i], self.stops[(i + 1) % self.stops.len()]))
+            .collect()
+    }
+
+    pub fn set_line_style(&mut self, line_style: LineStyle) {
+        if line_style != self.line_style {
+            self.line_style = line_style;
+            self.dirty = true;
+        }
+    }
+
+    pub fn set_marking(&mut self, marking: LineMarking) {
+        if marking != self.marking {
+            self.marking = marking;
+            self.mesh_dirty = true;
+        }
+    }
+
+    pub fn set_label_visible(&mut self, show_label: bool) {
+        self.show_label = show_label;
+    }
+
+    pub fn label(&self) -> String {
+        (self.id + 1).to_string()
+    }
+
+    pub fn set_lanes(&mut self, lanes: HashMap<(usize, usize), f32>) {
+        if lanes != self.lanes {
+            self.lanes = lanes;
+            self.dirty = true;
+        }
+    }
+
+    // Whether the path runs close enough to a disc that moving the disc could change it: through
+    // it, or around it on a detour.
+    pub fn passes_near(&self, center: Vec2, radius: f32) -> bool {
+        let reach = [Obstacle::new(center, radius + 2.0 * GRID_STEP)];
+        self.straight_segments()
+            .into_iter()
+            .any(|(from, to)| is_blocked(&[from, to], &reach))
+    }
+
+    pub fn straight_segments(&self) -> Vec<(Vec2, Vec2)> {
+        self.path_nodes
+            .iter()
+            .filter(|node| {
+                matches!(
+                    node.state(),
+                    VehicleState::Moving | VehicleState::Curve(_) | VehicleState::LastPlatform(_)
+                )
+            })
+            .map(|node| (node.begin_pos(), node.end_pos()))
+            .collect()
+    }
+
     pub fn calculate_position(&self, segment_id: usize, distance: f32) -> Vec2 {
         self.path_nodes[segment_id].calculate_position(distance)
     }
@@ -80,7 +392,182 @@ impl Route {
         self.path_nodes.len()
     }
 
-    fn update_route_segments(&mut self, stations: &StationHandler, routes_on_station: &Vec<u32>) {
+    pub fn total_length(&self) -> f32 {
+        self.offsets.last().copied().unwrap_or_default()
+    }
+
+    pub fn path_distance(&self, segment_id: usize, distance: f32) -> f32 {
+        self.offsets.get(segment_id).copied().unwrap_or_default() + distance
+    }
+
+    pub fn calls(&self) -> &Vec<(usize, f32)> {
+        &self.calls
+    }
+
+    pub fn timetable(&self) -> &Timetable {
+        &self.timetable
+    }
+
+    pub fn timetable_mut(&mut self) -> &mut Timetable {
+        &mut self.timetable
+    }
+
+    pub fn flow(&self) -> &SegmentFlow {
+        &self.flow
+    }
+
+    pub fn record_flow(&mut self, segment: usize, passengers: usize) {
+        self.flow.record(segment, passengers);
+    }
+
+    pub fn scheduled_departure(&self, trip_start: f32, call: usize) -> Option<f32> {
+        self.calls
+            .get(call)
+            .map(|&(_, distance)| trip_start + self.timetable.scheduled_offset(call, distance))
+    }
+
+    pub fn next_call(&self, path_distance: f32, direction: f32) -> Option<usize> {
+        let trip_distance = if direction < 0.0 && !self.is_looped {
+            2.0 * self.total_length() - path_distance
+        } else {
+            path_distance
+        };
+        self.calls
+            .iter()
+            .find(|&&(_, distance)| distance > trip_distance)
+            .or(self.calls.first())
+            .map(|&(station, _)| station)
+    }
+
+    fn update_offsets(&mut self) {
+        self.offsets.clear();
+        let mut distance = 0.0;
+        for node in self.path_nodes.iter() {
+            self.offsets.push(distance);
+            distance += node.length();
+        }
+        self.offsets.push(distance);
+    }
+
+    fn update_calls(&mut self) {
+        let previous = take(&mut self.calls);
+        self.calls.push((self.stops[0].index(), 0.0));
+
+        let mut distance = 0.0;
+        let mut forward_calls = vec![];
+        for node in self.path_nodes.iter() {
+            distance += node.length();
+            if let VehicleState::ArrivePlatform(..) = node.state() {
+                forward_calls.push((node.station(), distance));
+            }
+        }
+        if self.is_looped {
+            forward_calls.pop();
+            self.calls.extend(forward_calls);
+        } else {
+            self.calls.extend(forward_calls.iter().copied());
+            self.calls
+                .push((self.stops[self.stops.len() - 1].index(), distance));
+            self.calls.extend(
+                forward_calls
+                    .iter()
+                    .rev()
+                    .map(|&(station, d)| (station, 2.0 * distance - d)),
+            );
+        }
+        // Adherence is keyed by call index, which no longer lines up once the stops change.
+        if !previous
+            .iter()
+            .map(|&(station, _)| station)
+            .eq(self.calls.iter().map(|&(station, _)| station))
+        {
+            self.timetable.clear_adherence();
+        }
+    }
+
+    fn layout_cost(&self, others: &[(Vec2, Vec2)]) -> f32 {
+        let length: f32 = self.path_nodes.iter().map(|node| node.length()).sum();
+        let own = self.straight_segments();
+        let mut crossings = 0;
+        for (i, &a) in own.iter().enumerate() {
+            crossings += others.iter().filter(|&&b| segments_cross(a, b)).count();
+            crossings += own[i + 1..]
+                .iter()
+                .filter(|&&b| segments_cross(a, b))
+                .count();
+        }
+        length + CROSSING_PENALTY * crossings as f32
+    }
+
+    fn assign_stop_sides(
+        &mut self,
+        stations: &StationHandler,
+        platforms: &PlatformAllocator,
+        others: &[(Vec2, Vec2)],
+    ) {
+        // A flip only changes the legs either side of the stop, so every other leg is planned
+        // once and reused across trials.
+        let mut legs = LegPaths::new();
+        for _ in 0..SIDE_ASSIGNMENT_PASSES {
+            for idx in 0..self.stops.len() {
+                if self.stops[idx].is_manual() {
+                    continue;
+                }
+                let mut best = (f32::INFINITY, self.stops[idx].side());
+                for side in [StopSide::Left, StopSide::Right] {
+                    self.stops[idx].set_side(side);
+                    self.build_path(stations, platforms, &mut legs);
+                    let cost = self.layout_cost(others);
+                    if cost < best.0 {
+                        best = (cost, side);
+                    }
+                }
+                self.stops[idx].set_side(best.1);
+            }
+        }
+    }
+
+    // Discs of every station except the two ends of a leg, which the leg must not cut through.
+    fn obstacles(stations: &StationHandler, from: usize, to: usize) -> Vec<Obstacle> {
+        stations
+            .stations()
+            .iter()
+            .filter(|s| s.id() != from && s.id() != to)
+            .map(|s| Obstacle::new(s.position(), s.size()))
+            .collect()
+    }
+
+    fn update_route_segments(&mut self, stations: &StationHandler, platforms: &PlatformAllocator) {
+        self.build_path(stations, platforms, &mut LegPaths::new());
+    }
+
+    fn leg_bends(
+        stations: &StationHandler,
+        curr_stop: &Stop,
+        next_stop: &Stop,
+        points: [Vec2; 3],
+        legs: &mut LegPaths,
+    ) -> Vec<Vec2> {
+        let key = (curr_stop.index(), curr_stop.side(), next_stop.index(), next_stop.side());
+        legs.entry(key)
+            .or_insert_with(|| {
+                let obstacles = Self::obstacles(stations, curr_stop.index(), next_stop.index());
+                let [exit, turning_point, entrance] = points;
+                if is_blocked(&points, &obstacles) {
+                    plan_path(exit, entrance, &obstacles).unwrap_or_else(|| vec![turning_point])
+                } else {
+                    vec![turning_point]
+                }
+            })
+            .clone()
+    }
+
+    fn build_path(
+        &mut self,
+        stations: &StationHandler,
+        platforms: &PlatformAllocator,
+        legs: &mut LegPaths,
+    ) {
         self.path_nodes.clear();
 
         let mut platform_entrance: Vec2 = Vec2::new(0.0, 0.0);
@@ -94,13 +581,35 @@ impl Route {
             }
             let curr_station = stations.get(curr_stop.index());
             let next_station = stations.get(next_stop.index());
-            let curr_radius = self.calc_radius(curr_station, routes_on_station[curr_stop.index()]);
+            let curr_radius = self.calc_radius(curr_station, platforms);
             if idx == 0 {
                 first_radius = curr_radius;
             }
 
             let (platform_exit, turning_point, next_platform_entrance) =
-                self.calculate_turning_point(stations, &curr_stop, &next_stop, routes_on_station);
+                self.calculate_turning_point(stations, &curr_stop, &next_stop, platforms);
+            let exit = curr_station.position() + platform_exit;
+            let entrance = next_station.position() + next_platform_entrance;
+            // Detours are planned on the centre track and shifted into the lane with the rest
+            // of the leg, so every bend keeps its distance from the neighbouring lines.
+            let bends = if self.line_style == LineStyle::Octilinear {
+                Self::leg_bends(
+                    stations,
+                    &curr_stop,
+                    &next_stop,
+                    [exit, turning_point, entrance],
+                    legs,
+                )
+            } else {
+                vec![turning_point]
+            };
+            let points = self.offset_lane(
+                curr_station,
+                next_station,
+                once(exit).chain(bends).chain(once(entrance)).collect(),
+            );
+            let (exit, entrance) = (points[0], points[points.len() - 1]);
+            let platform_exit = exit - curr_station.position();
             if idx == 0 {
                 first_platform_exit = platform_exit;
             }
@@ -116,20 +625,28 @@ impl Route {
                 );
             }
 
-            platform_entrance = next_platform_entrance;
+            platform_entrance = entrance - next_station.position();
 
-            self.path_nodes.push(Segment::new(
-                VehicleState::Moving,
-                curr_station.position() + platform_exit,
-                turning_point,
-                curr_station.id(),
-            ));
-            self.path_nodes.push(Segment::new(
-                VehicleState::Moving,
-                turning_point,
-                next_station.position() + platform_entrance,
-                next_station.id(),
-            ));
+            if self.line_style == LineStyle::Octilinear {
+                for (i, pair) in points.windows(2).enumerate() {
+                    let station = if i == 0 { curr_station.id() } else { next_station.id() };
+                    self.path_nodes
+                        .push(Segment::new(VehicleState::Moving, pair[0], pair[1], station));
+                }
+                continue;
+            }
+            // A curved leg is the quadratic Bézier through the turning point, split in half so
+            // termini can still become straight LastPlatform segments.
+            let turning_point = points[1];
+            let (first, middle, second) = (
+                VehicleState::Curve((exit + turning_point) / 2.0),
+                (exit + 2.0 * turning_point + entrance) / 4.0,
+                VehicleState::Curve((turning_point + entrance) / 2.0),
+            );
+            self.path_nodes
+                .push(Segment::new(first, exit, middle, curr_station.id()));
+            self.path_nodes
+                .push(Segment::new(second, middle, entrance, next_station.id()));
         }
         if self.is_looped {
             self.update_platform_segments(
@@ -307,8 +824,77 @@ impl Route {
         ));
     }
 
-    fn calc_radius(&self, station: &Station, route_count: u32) -> f32 {
-        station.size() + (ROUTE_LINE_WIDTH + PLATFORM_GAP_WIDTH) * (route_count as f32)
+    // Shifts a leg into its lane. Every straight piece moves along its own normal, so it keeps
+    // its angle and runs exactly `lane` from where it was; bends go where the shifted pieces
+    // meet, and the two ends slide along their piece back onto the platform ring.
+    fn offset_lane(
+        &self,
+        curr_station: &Station,
+        next_station: &Station,
+        points: Vec<Vec2>,
+    ) -> Vec<Vec2> {
+        let (from, to) = if curr_station.id() < next_station.id() {
+            (curr_station, next_station)
+        } else {
+            (next_station, curr_station)
+        };
+        let Some(&lane) = self.lanes.get(&(from.id(), to.id())) else {
+            return points;
+        };
+        let side = (to.position() - from.position()).normalize_or_zero().perp();
+        let last = points.len() - 1;
+        let handedness = if (points[last] - points[0]).perp().dot(side) < 0.0 {
+            -1.0
+        } else {
+            1.0
+        };
+        let directions: Vec<Vec2> = points
+            .windows(2)
+            .map(|pair| (pair[1] - pair[0]).normalize_or_zero())
+            .collect();
+        let normals: Vec<Vec2> = directions
+            .iter()
+            .map(|direction| {
+                if *direction == Vec2::ZERO {
+                    side
+                } else {
+                    direction.perp() * handedness
+                }
+            })
+            .collect();
+
+        let mut offset = Vec::with_capacity(points.len());
+        for (i, &point) in points.iter().enumerate() {
+            offset.push(if i == 0 {
+                onto_ring(
+                    point + normals[0] * lane,
+                    directions[0],
+                    curr_station.position(),
+                    point.distance(curr_station.position()),
+                )
+            } else if i == last {
+                onto_ring(
+                    point + normals[i - 1] * lane,
+                    directions[i - 1],
+                    next_station.position(),
+                    point.distance(next_station.position()),
+                )
+            } else {
+                line_intersection(
+                    point + normals[i - 1] * lane,
+                    directions[i - 1],
+                    point + normals[i] * lane,
+                    directions[i],
+                )
+                .unwrap_or(point + normals[i] * lane)
+            });
+        }
+        offset
+    }
+
+    fn calc_radius(&self, station: &Station, platforms: &PlatformAllocator) -> f32 {
+        let slot = platforms.slot(station.id(), self.id);
+        station.size() + (ROUTE_LINE_WIDTH + PLATFORM_GAP_WIDTH) * (slot as f32)
     }
 
     fn calculate_turning_point(
@@ -316,13 +902,12 @@ impl Route {
         stations: &StationHandler,
         curr_stop: &Stop,
         next_stop: &Stop,
-        routes_on_station: &Vec<u32>,
+        platforms: &PlatformAllocator,
     ) -> (Vec2, Vec2, Vec2) {
         let mut begin_stop = curr_stop.index();
         let mut end_stop: usize = next_stop.index();
-        let mut curr_radius =
-            self.calc_radius(stations.get(begin_stop), routes_on_station[begin_stop]);
-        let mut next_radius = self.calc_radius(stations.get(end_stop), routes_on_station[end_stop]);
+        let mut curr_radius = self.calc_radius(stations.get(begin_stop), platforms);
+        let mut next_radius = self.calc_radius(stations.get(end_stop), platforms);
         let reverse_factor = if curr_radius < next_radius { -1.0 } else { 1.0 };
         if reverse_factor < 0.0 {
             swap(&mut curr_radius, &mut next_radius);
@@ -363,63 +948,88 @@ impl Route {
         }
     }
 
+    // The line marking only applies here; build_segment stays solid for overlays such as the
+    // heatmap.
     fn draw_path_mesh(&self, ctx: &Context) -> Mesh {
         let mut mb = MeshBuilder::new();
+        let pattern = self.dash_pattern();
+        // The track between two platforms is dashed as one polyline, so the pattern runs on
+        // across segment joints instead of restarting or being filled in by joint caps.
+        let mut track: Vec<Vec2> = vec![];
         for node in self.path_nodes.iter() {
-            self.build_segment(&mut mb, node);
+            let points = match node.state() {
+                VehicleState::Moving if pattern.is_some() => vec![node.begin_pos(), node.end_pos()],
+                VehicleState::Curve(control) if pattern.is_some() => (0..=CURVE_SMOOTHNESS)
+                    .map(|i| node.curve_point(control, i as f32 / CURVE_SMOOTHNESS as f32))
+                    .collect(),
+                state => {
+                    self.build_dashes(&mut mb, &take(&mut track), pattern);
+                    match state {
+                        VehicleState::LastPlatform(direction)
+                            if self.marking == LineMarking::Glyphs =>
+                        {
+                            Self::build_terminus(
+                                &mut mb,
+                                node,
+                                direction,
+                                ROUTE_LINE_WIDTH,
+                                self.color,
+                                Some(self.id % GLYPH_COUNT),
+                            );
+                        }
+                        _ => self.build_segment(&mut mb, node, self.color, ROUTE_LINE_WIDTH),
+                    }
+                    continue;
+                }
+            };
+            let skip = usize::from(track.last() == points.first());
+            track.extend(points.into_iter().skip(skip));
         }
+        self.build_dashes(&mut mb, &track, pattern);
         Mesh::from_data(ctx, mb.build())
     }
 
-    pub fn build_segment(&self, mb: &mut MeshBuilder, node: &Segment) {
+    fn dash_pattern(&self) -> Option<&'static [f32]> {
+        Some(DASH_PATTERNS[self.id % DASH_PATTERNS.len()])
+            .filter(|pattern| self.marking == LineMarking::Dashes && !pattern.is_empty())
+    }
+
+    pub fn build_segment(&self, mb: &mut MeshBuilder, node: &Segment, color: Color, width: f32) {
+        let platform_width = width * PLATFORM_LINE_WIDTH / ROUTE_LINE_WIDTH;
         match node.state() {
             VehicleState::Moving => {
-                mb.line(
-                    &[node.begin_pos(), node.end_pos()],
-                    ROUTE_LINE_WIDTH,
-                    self.color,
-                )
-                .expect("Error creating route mesh");
+                mb.line(&[node.begin_pos(), node.end_pos()], width, color)
+                    .expect("Error creating route mesh");
                 mb.circle(
                     DrawMode::fill(),
                     node.begin_pos(),
-                    ROUTE_LINE_WIDTH / 2.0,
+                    width / 2.0,
                     0.1,
-                    self.color,
+                    color,
                 )
                 .expect("Error creating route mesh");
                 mb.circle(
                     DrawMode::fill(),
                     node.end_pos(),
-                    ROUTE_LINE_WIDTH / 2.0,
+                    width / 2.0,
                     0.1,
-                    self.color,
+                    color,
                 )
                 .expect("Error creating route mesh");
             }
+            VehicleState::Curve(control) => {
+                let points: Vec<Vec2> = (0..=CURVE_SMOOTHNESS)
+                    .map(|i| node.curve_point(control, i as f32 / CURVE_SMOOTHNESS as f32))
+                    .collect();
+                mb.line(&points, width, color)
+                    .expect("Error creating route mesh");
+                mb.circle(DrawMode::fill(), node.begin_pos(), width / 2.0, 0.1, color)
+                    .expect("Error creating route mesh");
+                mb.circle(DrawMode::fill(), node.end_pos(), width / 2.0, 0.1, color)
+                    .expect("Error creating route mesh");
+            }
             VehicleState::LastPlatform(direction) => {
-                mb.line(
-                    &[node.begin_pos(), node.end_pos()],
-                    ROUTE_LINE_WIDTH,
-                    self.color,
-                )
-                .expect("Error creating route mesh");
-
-                let end_node = if direction == 1.0 {
-                    node.end_pos()
-                } else {
-                    node.begin_pos()
-                };
-                let perp = (node.end_pos() - node.begin_pos()).perp().normalize();
-                mb.line(
-                    &[
-                        end_node + perp * ROUTE_LINE_WIDTH,
-                        end_node - perp * ROUTE_LINE_WIDTH,
-                    ],
-                    ROUTE_LINE_WIDTH + 1.0,
-                    self.color,
-                )
-                .expect("Error creating route mesh");
+                Self::build_terminus(mb, node, direction, width, color, None);
             }
             VehicleState::ArrivePlatform(
                 center,
@@ -439,7 +1049,7 @@ impl Route {
                     );
                     points.push((center + Vec2::from_angle(angle) * radius).to_array());
                 }
-                mb.line(&points, PLATFORM_LINE_WIDTH, self.color)
+                mb.line(&points, platform_width, color)
                     .expect("Error creating route mesh");
             }
             VehicleState::LeavePlatform(
@@ -455,26 +1065,101 @@ impl Route {
                     let angle = lerp_angle(center_angle, exit_angle, (i as f32) / 20.0, false);
                     points.push((center + Vec2::from_angle(angle) * radius).to_array());
                 }
-                mb.line(&points, PLATFORM_LINE_WIDTH, self.color)
+                mb.line(&points, platform_width, color)
                     .expect("Error creating route mesh");
             }
         }
     }
 
+    fn build_dashes(&self, mb: &mut MeshBuilder, points: &[Vec2], pattern: Option<&[f32]>) {
+        let Some(pattern) = pattern.filter(|_| points.len() > 1) else {
+            return;
+        };
+        for dash in dash_polyline(points, pattern) {
+            mb.line(&dash, ROUTE_LINE_WIDTH, self.color)
+                .expect("Error creating route mesh");
+        }
+    }
+
+    // The last stretch of an open line, ended by a bar or, when lines are marked by glyphs,
+    // by the line's glyph.
+    fn build_terminus(
+        mb: &mut MeshBuilder,
+        node: &Segment,
+        direction: f32,
+        width: f32,
+        color: Color,
+        glyph: Option<usize>,
+    ) {
+        mb.line(
+            &[node.begin_pos(), node.end_pos()],
+            width,
+            color,
+        )
+        .expect("Error creating route mesh");
+
+        let (end_node, outward) = Self::terminus_end(node, direction);
+        if let Some(glyph) = glyph {
+            build_glyph(mb, glyph, end_node, outward, width, color);
+            return;
+        }
+        let perp = (node.end_pos() - node.begin_pos()).perp().normalize();
+        mb.line(
+            &[
+                end_node + perp * width,
+                end_node - perp * width,
+            ],
+            width + 1.0,
+            color,
+        )
+        .expect("Error creating route mesh");
+    }
+
+    fn terminus_end(node: &Segment, direction: f32) -> (Vec2, Vec2) {
+        let outward = (node.end_pos() - node.begin_pos()).normalize_or_zero();
+        if direction == 1.0 {
+            (node.end_pos(), outward)
+        } else {
+            (node.begin_pos(), -outward)
+        }
+    }
+
+    // Brings the path up to date without the rest of a rebuild, so other routes can lay out
+    // their stops against it.
+    pub fn update_path(&mut self, stations: &StationHandler, platforms: &PlatformAllocator) {
+        self.update_route_segments(stations, platforms);
+    }
+
+    pub fn layout_stops(
+        &mut self,
+        stations: &StationHandler,
+        platforms: &PlatformAllocator,
+        others: &[(Vec2, Vec2)],
+    ) {
+        self.assign_stop_sides(stations, platforms, others);
+        self.update_route_segments(stations, platforms);
+    }
+
     pub fn update(
         &mut self,
         ctx: &Context,
         stations: &StationHandler,
-        routes_on_station: &mut Vec<u32>,
+        platforms: &PlatformAllocator,
         delta: f32,
     ) {
+        self.timetable.update(delta);
+        self.flow.update(delta);
         if self.dirty {
-            self.update_route_segments(stations, routes_on_station);
+            self.update_route_segments(stations, platforms);
+            self.flow.follow(&self.path_nodes);
+            self.update_offsets();
+            self.update_calls();
             self.mesh = Some(self.draw_path_mesh(ctx));
             self.dirty = false;
-        }
-        for idx in 1..self.stops.len() - 1 {
-            routes_on_station[self.stops[idx].index()] += 1;
+            self.mesh_dirty = false;
+        } else if self.mesh_dirty {
+            self.mesh = Some(self.draw_path_mesh(ctx));
+            self.mesh_dirty = false;
         }
     }
 
@@ -482,5 +1167,122 @@ impl Route {
         if let Some(mesh) = &self.mesh {
             canvas.draw(mesh, DrawParam::default());
         }
+        if !self.show_label {
+            return;
+        }
+        let mut text = Text::new(self.label());
+        text.set_scale(LABEL_SIZE);
+        let size = text.measure(ctx).map(Vec2::from).unwrap_or_default();
+        for node in self.path_nodes.iter() {
+            if let VehicleState::LastPlatform(direction) = node.state() {
+                let (end_node, outward) = Self::terminus_end(node, direction);
+                canvas.draw(
+                    &text,
+                    DrawParam::default()
+                        .dest(end_node + outward * LABEL_OFFSET - size / 2.0)
+                        .color(Colors::text()),
+                );
+            }
+        }
+    }
+}
+
+// Splits a polyline into the drawn stretches of an alternating dash/gap `pattern`.
+// Where `point` meets the ring when moved along `direction`, taking the nearer crossing.
+fn onto_ring(point: Vec2, direction: Vec2, center: Vec2, radius: f32) -> Vec2 {
+    let from_center = point - center;
+    let along = from_center.dot(direction);
+    let discriminant = along * along - (from_center.length_squared() - radius * radius);
+    if discriminant < 0.0 {
+        return point;
+    }
+    let root = discriminant.sqrt();
+    let t = if (-along - root).abs() < (-along + root).abs() {
+        -along - root
+    } else {
+        -along + root
+    };
+    point + direction * t
+}
+
+// Where the line through `a` along `da` meets the line through `b` along `db`.
+fn line_intersection(a: Vec2, da: Vec2, b: Vec2, db: Vec2) -> Option<Vec2> {
+    let denominator = da.perp_dot(db);
+    if denominator.abs() < f32::EPSILON {
+        return None;
+    }
+    Some(a + da * ((b - a).perp_dot(db) / denominator))
+}
+
+fn dash_polyline(points: &[Vec2], pattern: &[f32]) -> Vec<Vec<Vec2>> {
+    let mut dashes = vec![];
+    let mut dash = vec![];
+    let (mut step, mut left) = (0, pattern[0]);
+    for pair in points.windows(2) {
+        let (mut from, to) = (pair[0], pair[1]);
+        let mut length = from.distance(to);
+        while length > 0.0 {
+            let drawing = step % 2 == 0;
+            if drawing && dash.is_empty() {
+                dash.push(from);
+            }
+            let advance = left.min(length);
+            from += (to - from) * (advance / length);
+            length -= advance;
+            left -= advance;
+            if drawing {
+                dash.push(from);
+            }
+            if left <= 0.0 {
+                if drawing {
+                    dashes.push(take(&mut dash));
+                }
+                step = (step + 1) % pattern.len();
+                left = pattern[step];
+            }
+        }
     }
+    if dash.len() > 1 {
+        dashes.push(dash);
+    }
+    dashes
+}
+
+fn build_glyph(
+    mb: &mut MeshBuilder,
+    glyph: usize,
+    at: Vec2,
+    outward: Vec2,
+    width: f32,
+    color: Color,
+) {
+    let size = width * GLYPH_SCALE;
+    let (forward, side) = (outward * size, outward.perp() * size);
+    let result = match glyph {
+        0 => mb.circle(DrawMode::fill(), at, size, 0.1, color),
+        1 => mb.polygon(
+            DrawMode::fill(),
+            &[
+                at + side,
+                at + side + forward * 2.0,
+                at - side + forward * 2.0,
+                at - side,
+            ],
+            color,
+        ),
+        2 => mb.polygon(
+            DrawMode::fill(),
+            &[at + side * 1.2, at + forward * 2.0, at - side * 1.2],
+            color,
+        ),
+        3 => mb.polygon(
+            DrawMode::fill(),
+            &[at + side, at + forward, at - side, at - forward],
+            color,
+        ),
+        _ => mb
+            .line(&[at + side, at - side], width, color)
+            .and_then(|mb| mb.line(&[at + side - forward, at - side - forward], width, color)),
+    };
+    result.expect("Error creating route mesh");
 }
@@ -0,0 +1,180 @@
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashMap},
+    f32::consts::SQRT_2,
+};
+
+use ggez::glam::{IVec2, Vec2};
+
+pub const OBSTACLE_MARGIN: f32 = 6.0;
+pub const GRID_STEP: f32 = 20.0;
+pub const SEARCH_MARGIN: f32 = 200.0;
+pub const BEND_PENALTY: f32 = 15.0;
+pub const MAX_EXPANSIONS: usize = 5000;
+
+const DIRECTIONS: [IVec2; 8] = [
+    IVec2::new(1, 0),
+    IVec2::new(1, 1),
+    IVec2::new(0, 1),
+    IVec2::new(-1, 1),
+    IVec2::new(-1, 0),
+    IVec2::new(-1, -1),
+    IVec2::new(0, -1),
+    IVec2::new(1, -1),
+];
+
+#[derive(Clone, Copy)]
+pub struct Obstacle {
+    center: Vec2,
+    radius: f32,
+}
+
+impl Obstacle {
+    pub fn new(center: Vec2, radius: f32) -> Self {
+        Obstacle {
+            center,
+            radius: radius + OBSTACLE_MARGIN,
+        }
+    }
+
+    fn blocks(&self, from: Vec2, to: Vec2) -> bool {
+        let segment = to - from;
+        let t = if segment.length_squared() > 0.0 {
+            ((self.center - from).dot(segment) / segment.length_squared()).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        (from + segment * t).distance(self.center) < self.radius
+    }
+}
+
+pub fn is_blocked(points: &[Vec2], obstacles: &[Obstacle]) -> bool {
+    points
+        .windows(2)
+        .any(|pair| obstacles.iter().any(|o| o.blocks(pair[0], pair[1])))
+}
+
+// The two octilinear elbows joining `from` and `to`: straight then diagonal, and diagonal then
+// straight.
+fn elbows(from: Vec2, to: Vec2) -> [Vec2; 2] {
+    let d = to - from;
+    let diagonal = d.x.abs().min(d.y.abs());
+    let straight = if d.x.abs() > d.y.abs() {
+        Vec2::new(d.x.signum() * (d.x.abs() - diagonal), 0.0)
+    } else {
+        Vec2::new(0.0, d.y.signum() * (d.y.abs() - diagonal))
+    };
+    [from + straight, to - straight]
+}
+
+#[derive(PartialEq)]
+struct Open {
+    cost: f32,
+    node: (IVec2, usize),
+}
+
+impl Eq for Open {}
+
+impl Ord for Open {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.cost.total_cmp(&self.cost)
+    }
+}
+
+impl PartialOrd for Open {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+fn octile_distance(from: Vec2, to: Vec2) -> f32 {
+    let d = (to - from).abs();
+    d.max_element() + (SQRT_2 - 1.0) * d.min_element()
+}
+
+// Octilinear A* over a lattice anchored at `from`. Returns the bend points between `from` and
+// `to`, or None if no clear path is found within the search budget.
+pub fn plan_path(from: Vec2, to: Vec2, obstacles: &[Obstacle]) -> Option<Vec<Vec2>> {
+    for elbow in elbows(from, to) {
+        if !is_blocked(&[from, elbow, to], obstacles) {
+            return Some(vec![elbow]);
+        }
+    }
+
+    let position = |cell: IVec2| from + cell.as_vec2() * GRID_STEP;
+    let min = from.min(to) - Vec2::splat(SEARCH_MARGIN);
+    let max = from.max(to) + Vec2::splat(SEARCH_MARGIN);
+    let in_bounds = |p: Vec2| p.cmpge(min).all() && p.cmple(max).all();
+
+    let mut open = BinaryHeap::new();
+    let mut costs: HashMap<(IVec2, usize), f32> = HashMap::new();
+    let mut parents: HashMap<(IVec2, usize), (IVec2, usize)> = HashMap::new();
+    for dir in 0..DIRECTIONS.len() {
+        costs.insert((IVec2::ZERO, dir), 0.0);
+        open.push(Open {
+            cost: octile_distance(from, to),
+            node: (IVec2::ZERO, dir),
+        });
+    }
+
+    let mut expansions = 0;
+    while let Some(Open { node, .. }) = open.pop() {
+        expansions += 1;
+        if expansions > MAX_EXPANSIONS {
+            return None;
+        }
+        let (cell, dir) = node;
+        let here = position(cell);
+        let finish = elbows(here, to)
+            .into_iter()
+            .find(|&elbow| !is_blocked(&[here, elbow, to], obstacles));
+        if let Some(elbow) = finish {
+            let mut cells = vec![cell];
+            let mut current = node;
+            while let Some(&parent) = parents.get(&current) {
+                cells.push(parent.0);
+                current = parent;
+            }
+            cells.reverse();
+            let mut points: Vec<Vec2> = cells.into_iter().map(position).collect();
+            points.push(elbow);
+            points.push(to);
+            return Some(simplify(points));
+        }
+
+        let cost = costs[&node];
+        for (next_dir, step) in DIRECTIONS.iter().enumerate() {
+            let next_cell = cell + *step;
+            let next = position(next_cell);
+            if !in_bounds(next) || is_blocked(&[here, next], obstacles) {
+                continue;
+            }
+            let bend = if next_dir == dir { 0.0 } else { BEND_PENALTY };
+            let next_cost = cost + here.distance(next) + bend;
+            let key = (next_cell, next_dir);
+            if costs.get(&key).is_some_and(|&c| c <= next_cost) {
+                continue;
+            }
+            costs.insert(key, next_cost);
+            parents.insert(key, node);
+            open.push(Open {
+                cost: next_cost + octile_distance(next, to),
+                node: key,
+            });
+        }
+    }
+    None
+}
+
+// Drops the start, the end and every point that lies on a straight run.
+fn simplify(points: Vec<Vec2>) -> Vec<Vec2> {
+    let mut bends = vec![];
+    for i in 1..points.len() - 1 {
+        let incoming = (points[i] - points[i - 1]).normalize_or_zero();
+        let outgoing = (points[i + 1] - points[i]).normalize_or_zero();
+        if incoming.distance(outgoing) > 1e-3 && outgoing != Vec2::ZERO {
+            bends.push(points[i]);
+        }
+    }
+    bends
+}
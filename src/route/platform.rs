@@ -0,0 +1,44 @@
+use std::collections::HashMap;
+
+use super::Route;
+
+pub struct PlatformAllocator {
+    slots: HashMap<(usize, usize), u32>,
+}
+
+impl PlatformAllocator {
+    pub fn new() -> Self {
+        PlatformAllocator {
+            slots: HashMap::new(),
+        }
+    }
+
+    // Every route serving a station gets its own ring, innermost first by route id. A route
+    // calling at the same station more than once keeps a single platform there.
+    pub fn allocate(&mut self, routes: &[Route]) -> bool {
+        let mut serving: HashMap<usize, Vec<usize>> = HashMap::new();
+        for route in routes {
+            for stop in route.stops() {
+                let ids = serving.entry(stop.index()).or_default();
+                if !ids.contains(&route.id()) {
+                    ids.push(route.id());
+                }
+            }
+        }
+
+        let mut slots = HashMap::new();
+        for (station, mut ids) in serving {
+            ids.sort();
+            for (slot, id) in ids.into_iter().enumerate() {
+                slots.insert((station, id), slot as u32 + 1);
+            }
+        }
+        let changed = slots != self.slots;
+        self.slots = slots;
+        changed
+    }
+
+    pub fn slot(&self, station: usize, route: usize) -> u32 {
+        self.slots.get(&(station, route)).copied().unwrap_or(1)
+    }
+}
@@ -4,29 +4,49 @@ use ggez::glam::Vec2;
 
 use crate::utils::{angle_between, lerp_angle};
 
+const CURVE_SAMPLES: usize = 24;
+
 #[derive(Clone, Copy, PartialEq)]
 pub enum VehicleState {
     Moving,
+    Curve(Vec2),
     LastPlatform(f32),
     ArrivePlatform(Vec2, f32, f32, f32, f32, bool),
     LeavePlatform(Vec2, f32, f32, f32, f32, bool),
 }
 
+impl VehicleState {
+    pub fn name(&self) -> &'static str {
+        match self {
+            VehicleState::Moving => "Moving",
+            VehicleState::Curve(_) => "Curve",
+            VehicleState::LastPlatform(_) => "LastPlatform",
+            VehicleState::ArrivePlatform(..) => "ArrivePlatform",
+            VehicleState::LeavePlatform(..) => "LeavePlatform",
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
 pub struct Segment {
     begin_pos: Vec2,
     end_pos: Vec2,
     connecting_station: usize,
     state: VehicleState,
+    curve_lengths: [f32; CURVE_SAMPLES + 1],
 }
 
 impl Segment {
     pub fn new(state: VehicleState, begin_pos: Vec2, end_pos: Vec2, connecting_station: usize) -> Self {
-        Segment {
+        let mut segment = Segment {
             state,
             begin_pos,
             end_pos,
-            connecting_station
-        }
+            connecting_station,
+            curve_lengths: [0.0; CURVE_SAMPLES + 1],
+        };
+        segment.update_curve_lengths();
+        segment
     }
 
     pub fn begin_pos(&self) -> Vec2 {
@@ -41,11 +61,18 @@ impl Segment {
         self.state
     }
 
+    pub fn same_geometry(&self, other: &Segment) -> bool {
+        self.begin_pos == other.begin_pos
+            && self.end_pos == other.end_pos
+            && self.state == other.state
+    }
+
     pub fn length(&self) -> f32 {
         match self.state {
             VehicleState::Moving | VehicleState::LastPlatform(_) => {
                 (self.end_pos - self.begin_pos).length()
             }
+            VehicleState::Curve(_) => self.curve_lengths[CURVE_SAMPLES],
             VehicleState::ArrivePlatform(_, radius, entrance_angle, center_angle, _, _) => {
                 radius * angle_between(center_angle, entrance_angle)
             }
@@ -89,6 +116,9 @@ impl Segment {
                 self.begin_pos
                     + (self.end_pos - self.begin_pos) * (distance / self.length()).min(1.0)
             }
+            VehicleState::Curve(control) => {
+                self.curve_point(control, self.curve_parameter(distance))
+            }
             VehicleState::ArrivePlatform(center, radius, entrance_angle, center_angle, _, _) => {
                 let angle = lerp_angle(
                     entrance_angle,
@@ -116,6 +146,11 @@ impl Segment {
                 let vec = self.end_pos - self.begin_pos;
                 vec.y.atan2(vec.x)
             }
+            VehicleState::Curve(control) => {
+                let t = self.curve_parameter(distance);
+                let tangent = (control - self.begin_pos) * (1.0 - t) + (self.end_pos - control) * t;
+                tangent.y.atan2(tangent.x)
+            }
             VehicleState::ArrivePlatform(_, _, entrance_angle, center_angle, _, choose_larger) => {
                 self.calculate_rotation_on_platform(entrance_angle, center_angle, distance)
             }
@@ -125,6 +160,42 @@ impl Segment {
         }
     }
 
+    pub fn curve_point(&self, control: Vec2, t: f32) -> Vec2 {
+        let u = 1.0 - t;
+        self.begin_pos * u * u + control * 2.0 * u * t + self.end_pos * t * t
+    }
+
+    // Cumulative arc length at evenly spaced curve parameters, so trains can move along the
+    // curve at constant speed. Computed once whenever the segment's shape is set.
+    fn update_curve_lengths(&mut self) {
+        let VehicleState::Curve(control) = self.state else {
+            return;
+        };
+        let mut previous = self.begin_pos;
+        for i in 1..=CURVE_SAMPLES {
+            let point = self.curve_point(control, i as f32 / CURVE_SAMPLES as f32);
+            self.curve_lengths[i] = self.curve_lengths[i - 1] + point.distance(previous);
+            previous = point;
+        }
+    }
+
+    fn curve_parameter(&self, distance: f32) -> f32 {
+        let lengths = &self.curve_lengths;
+        let distance = distance.clamp(0.0, lengths[CURVE_SAMPLES]);
+        let i = lengths
+            .iter()
+            .position(|&length| length >= distance)
+            .unwrap_or(CURVE_SAMPLES)
+            .max(1);
+        let (l0, l1) = (lengths[i - 1], lengths[i]);
+        let t0 = (i - 1) as f32 / CURVE_SAMPLES as f32;
+        if l1 > l0 {
+            t0 + (distance - l0) / (l1 - l0) / CURVE_SAMPLES as f32
+        } else {
+            i as f32 / CURVE_SAMPLES as f32
+        }
+    }
+
     fn calculate_rotation_on_platform(&self, from: f32, to: f32, distance: f32) -> f32 {
         let angle = lerp_angle(
             from,
@@ -149,6 +220,7 @@ impl Segment {
 
     pub fn set_state(&mut self, state: VehicleState) {
         self.state = state;
+        self.update_curve_lengths();
     }
     
     pub fn station(&self) -> usize {
@@ -1,4 +1,4 @@
-#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
 pub enum StopSide {
     Left = -1,
     Right = 1
@@ -7,17 +7,28 @@ pub enum StopSide {
 #[derive(Clone, Copy)]
 pub struct Stop {
     index: usize,
-    side: StopSide
+    side: StopSide,
+    manual: bool
 }
 
 impl Stop {
     pub fn new(index: usize, side: StopSide) -> Self {
-        Stop { index, side }
+        Stop { index, side, manual: true }
+    }
+
+    pub fn auto(index: usize) -> Self {
+        Stop { index, side: StopSide::Right, manual: false }
     }
 
     pub fn index(&self) -> usize { self.index }
 
+    pub fn set_index(&mut self, index: usize) { self.index = index; }
+
     pub fn side(&self) -> StopSide { self.side }
 
     pub fn side_factor(&self) -> f32 { self.side as i32 as f32 }
+
+    pub fn is_manual(&self) -> bool { self.manual }
+
+    pub fn set_side(&mut self, side: StopSide) { self.side = side; }
 }
\ No newline at end of file
@@ -0,0 +1,176 @@
+use std::collections::HashMap;
+
+pub const DEFAULT_TIMETABLE_PERIOD: f32 = 60.0;
+pub const DEFAULT_CRUISE_SPEED: f32 = 150.0;
+pub const DEFAULT_DWELL_TIME: f32 = 1.0;
+pub const ADHERENCE_TOLERANCE: f32 = 1.0;
+
+#[derive(Clone, Copy, Default, Debug)]
+pub struct Adherence {
+    early: u32,
+    on_time: u32,
+    late: u32,
+    total_deviation: f32,
+}
+
+impl Adherence {
+    pub fn early(&self) -> u32 {
+        self.early
+    }
+
+    pub fn on_time(&self) -> u32 {
+        self.on_time
+    }
+
+    pub fn late(&self) -> u32 {
+        self.late
+    }
+
+    pub fn count(&self) -> u32 {
+        self.early + self.on_time + self.late
+    }
+
+    pub fn mean_deviation(&self) -> f32 {
+        if self.count() == 0 {
+            0.0
+        } else {
+            self.total_deviation / self.count() as f32
+        }
+    }
+
+    fn record(&mut self, deviation: f32) {
+        if deviation < -ADHERENCE_TOLERANCE {
+            self.early += 1;
+        } else if deviation > ADHERENCE_TOLERANCE {
+            self.late += 1;
+        } else {
+            self.on_time += 1;
+        }
+        self.total_deviation += deviation;
+    }
+}
+
+pub struct Timetable {
+    enabled: bool,
+    period: f32,
+    departures: Vec<f32>,
+    cruise_speed: f32,
+    dwell_time: f32,
+    clock: f32,
+    last_dispatch: f32,
+    adherence: HashMap<usize, Adherence>,
+}
+
+impl Timetable {
+    pub fn new() -> Self {
+        Timetable {
+            enabled: false,
+            period: DEFAULT_TIMETABLE_PERIOD,
+            departures: vec![0.0, DEFAULT_TIMETABLE_PERIOD / 2.0],
+            cruise_speed: DEFAULT_CRUISE_SPEED,
+            dwell_time: DEFAULT_DWELL_TIME,
+            clock: 0.0,
+            last_dispatch: f32::NEG_INFINITY,
+            adherence: HashMap::new(),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        self.last_dispatch = f32::NEG_INFINITY;
+    }
+
+    pub fn clock(&self) -> f32 {
+        self.clock
+    }
+
+    pub fn period(&self) -> f32 {
+        self.period
+    }
+
+    pub fn set_period(&mut self, period: f32) {
+        self.period = period.max(1.0);
+        for departure in self.departures.iter_mut() {
+            *departure = departure.min(self.period - 1.0).max(0.0);
+        }
+        self.sort_departures();
+    }
+
+    pub fn departures(&self) -> &Vec<f32> {
+        &self.departures
+    }
+
+    pub fn dwell_time(&self) -> f32 {
+        self.dwell_time
+    }
+
+    pub fn add_departure(&mut self, time: f32) -> usize {
+        let time = time.rem_euclid(self.period);
+        self.departures.push(time);
+        self.sort_departures();
+        self.departures
+            .iter()
+            .position(|&d| d == time)
+            .unwrap_or_default()
+    }
+
+    pub fn remove_departure(&mut self, index: usize) {
+        if index < self.departures.len() {
+            self.departures.remove(index);
+        }
+    }
+
+    pub fn shift_departure(&mut self, index: usize, offset: f32) -> usize {
+        if index >= self.departures.len() {
+            return index;
+        }
+        let time = self.departures.remove(index) + offset;
+        self.add_departure(time)
+    }
+
+    pub fn update(&mut self, delta: f32) {
+        self.clock += delta;
+    }
+
+    pub fn scheduled_offset(&self, call: usize, call_distance: f32) -> f32 {
+        call_distance / self.cruise_speed + call as f32 * self.dwell_time
+    }
+
+    pub fn dispatch(&mut self) -> Option<f32> {
+        if self.departures.is_empty() {
+            return None;
+        }
+        let after = self.last_dispatch.max(self.clock - self.period / 2.0);
+        let base = (after / self.period).floor() * self.period;
+        let period = self.period;
+        let departures = &self.departures;
+        let departure = (0..=1)
+            .flat_map(|k| departures.iter().map(move |d| base + k as f32 * period + d))
+            .find(|&t| t > after)?;
+        self.last_dispatch = departure;
+        Some(departure)
+    }
+
+    // Keyed by the call's index in the round trip, so outbound and return calls at the same
+    // station are tracked separately.
+    pub fn record_arrival(&mut self, call: usize, deviation: f32) {
+        self.adherence.entry(call).or_default().record(deviation);
+    }
+
+    pub fn adherence(&self, call: usize) -> Adherence {
+        self.adherence.get(&call).copied().unwrap_or_default()
+    }
+
+    pub fn clear_adherence(&mut self) {
+        self.adherence.clear();
+    }
+
+    fn sort_departures(&mut self) {
+        self.departures.sort_by(|a, b| a.total_cmp(b));
+        self.departures.dedup();
+    }
+}
@@ -0,0 +1,194 @@
+use std::iter;
+
+use ggez::{
+    Context,
+    graphics::{Canvas, DrawParam, Quad, Rect},
+    input::keyboard::KeyCode,
+};
+use strum::IntoEnumIterator;
+
+use crate::{
+    edit::mode::GameMode,
+    ui::menu::{Menu, MenuAction},
+    utils::{colors::Colors, config::Settings},
+};
+
+use super::{FADE_DURATION, Scene, SceneEvent, Transition};
+
+pub struct SceneHandler {
+    stack: Vec<Scene>,
+    menu: Menu,
+    fade: f32,
+}
+
+impl SceneHandler {
+    pub fn new() -> Self {
+        SceneHandler {
+            stack: vec![Scene::MainMenu],
+            menu: Menu::new(),
+            fade: FADE_DURATION,
+        }
+    }
+
+    pub fn current(&self) -> Scene {
+        self.stack.last().copied().unwrap_or(Scene::MainMenu)
+    }
+
+    pub fn transition(&mut self, transition: Transition) {
+        match transition {
+            Transition::Push(scene) => self.stack.push(scene),
+            Transition::Pop => {
+                if self.stack.len() > 1 {
+                    self.stack.pop();
+                }
+            }
+            Transition::Reset(scene) => self.stack = vec![scene],
+        }
+        self.menu = Menu::new();
+        self.fade = FADE_DURATION;
+    }
+
+    pub fn update(&mut self, delta: f32) {
+        self.fade = (self.fade - delta).max(0.0);
+    }
+
+    fn items(&self, settings: &Settings) -> Vec<String> {
+        let labels: &[&str] = match self.current() {
+            Scene::MainMenu => &["Play", "Settings", "Quit"],
+            Scene::ScenarioSelect => {
+                return GameMode::iter()
+                    .map(|mode| format!("{:<10}{}", mode.name(), mode.description()))
+                    .chain(iter::once(String::from("Back")))
+                    .collect();
+            }
+            Scene::Settings => {
+                return vec![
+                    format!(
+                        "Window size     {}x{}",
+                        settings.window_width, settings.window_height
+                    ),
+                    format!(
+                        "Anti-aliasing   {}x (after restart)",
+                        u8::from(settings.samples)
+                    ),
+                    format!("Theme           {}", settings.theme.name()),
+                    format!("Game speed      {}x", settings.game_speed),
+                    format!("Line style      {}", settings.line_style.name()),
+                    format!("Line colours    {}", settings.line_palette.name()),
+                    format!("Line markings   {}", settings.line_marking.name()),
+                    format!(
+                        "Line labels     {}",
+                        if settings.line_labels { "on" } else { "off" }
+                    ),
+                    if settings.walking_radius > 0.0 {
+                        format!("Walking radius  {}", settings.walking_radius)
+                    } else {
+                        String::from("Walking radius  off")
+                    },
+                    String::from("Back"),
+                ];
+            }
+            Scene::Paused => &["Resume", "Statistics", "Settings", "Main menu"],
+            Scene::GameOver => &["Statistics", "Main menu"],
+            Scene::InGame | Scene::Statistics => &[],
+        };
+        labels.iter().map(|label| label.to_string()).collect()
+    }
+
+    pub fn handle_key(&mut self, keycode: KeyCode, settings: &mut Settings) -> Option<SceneEvent> {
+        let scene = self.current();
+        match (scene, keycode) {
+            (Scene::InGame, KeyCode::Escape) => self.transition(Transition::Push(Scene::Paused)),
+            (Scene::InGame, KeyCode::G) => self.transition(Transition::Push(Scene::Statistics)),
+            (Scene::Statistics, KeyCode::Escape | KeyCode::G) => self.transition(Transition::Pop),
+            _ => {}
+        }
+        if !scene.is_menu() {
+            return None;
+        }
+        let count = self.items(settings).len();
+        let action = self.menu.handle_key(keycode, count)?;
+        let index = match action {
+            MenuAction::Select(index) => index,
+            MenuAction::Adjust(index) if scene == Scene::Settings => index,
+            MenuAction::Adjust(..) => return None,
+            MenuAction::Back => {
+                if matches!(scene, Scene::MainMenu | Scene::GameOver) {
+                    return None;
+                }
+                self.transition(Transition::Pop);
+                return (scene == Scene::Settings).then_some(SceneEvent::SettingsChanged);
+            }
+        };
+        match (scene, index) {
+            (Scene::MainMenu, 0) => self.transition(Transition::Push(Scene::ScenarioSelect)),
+            (Scene::MainMenu, 1) | (Scene::Paused, 2) => {
+                self.transition(Transition::Push(Scene::Settings))
+            }
+            (Scene::MainMenu, _) => return Some(SceneEvent::Quit),
+            (Scene::ScenarioSelect, index) => match GameMode::iter().nth(index) {
+                Some(mode) => {
+                    self.transition(Transition::Reset(Scene::InGame));
+                    return Some(SceneEvent::Start(mode));
+                }
+                None => self.transition(Transition::Pop),
+            },
+            (Scene::Settings, index) if index == count - 1 => self.transition(Transition::Pop),
+            (Scene::Settings, index) => {
+                match index {
+                    0 => settings.next_window_size(),
+                    1 => settings.next_samples(),
+                    2 => settings.theme = settings.theme.next(),
+                    3 => settings.next_game_speed(),
+                    4 => settings.line_style = settings.line_style.next(),
+                    5 => settings.line_palette = settings.line_palette.next(),
+                    6 => settings.line_marking = settings.line_marking.next(),
+                    7 => settings.line_labels = !settings.line_labels,
+                    _ => settings.next_walking_radius(),
+                }
+                return Some(SceneEvent::SettingsChanged);
+            }
+            (Scene::Paused, 0) => self.transition(Transition::Pop),
+            (Scene::Paused, 1) | (Scene::GameOver, 0) => {
+                self.transition(Transition::Push(Scene::Statistics))
+            }
+            (Scene::Paused | Scene::GameOver, _) => {
+                self.transition(Transition::Reset(Scene::MainMenu))
+            }
+            (Scene::InGame | Scene::Statistics, _) => {}
+        }
+        None
+    }
+
+    pub fn draw(
+        &self,
+        ctx: &Context,
+        canvas: &mut Canvas,
+        viewport: Rect,
+        settings: &Settings,
+        game_over: Option<&str>,
+    ) {
+        let scene = self.current();
+        if scene.is_menu() {
+            let mut header = vec![scene.title().to_string()];
+            if scene == Scene::GameOver
+                && let Some(reason) = game_over
+            {
+                header.push(reason.to_string());
+            }
+            self.menu
+                .draw(ctx, canvas, viewport, &header, &self.items(settings));
+        }
+        if self.fade > 0.0 {
+            let mut color = Colors::background(1.0);
+            color.a = self.fade / FADE_DURATION;
+            canvas.draw(
+                &Quad,
+                DrawParam::default()
+                    .dest(viewport.point())
+                    .scale(viewport.size())
+                    .color(color),
+            );
+        }
+    }
+}
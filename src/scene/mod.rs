@@ -0,0 +1,47 @@
+use crate::edit::mode::GameMode;
+
+pub mod handler;
+
+pub const FADE_DURATION: f32 = 0.25;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Scene {
+    MainMenu,
+    ScenarioSelect,
+    Settings,
+    InGame,
+    Paused,
+    GameOver,
+    Statistics,
+}
+
+impl Scene {
+    pub fn title(&self) -> &'static str {
+        match self {
+            Scene::MainMenu => "Rusty Metro",
+            Scene::ScenarioSelect => "Choose a mode",
+            Scene::Settings => "Settings",
+            Scene::InGame => "",
+            Scene::Paused => "Paused",
+            Scene::GameOver => "Game over",
+            Scene::Statistics => "Statistics",
+        }
+    }
+
+    // The network is still drawn underneath, but only `InGame` takes gameplay input.
+    pub fn is_menu(&self) -> bool {
+        !matches!(self, Scene::InGame | Scene::Statistics)
+    }
+}
+
+pub enum Transition {
+    Push(Scene),
+    Pop,
+    Reset(Scene),
+}
+
+pub enum SceneEvent {
+    Start(GameMode),
+    SettingsChanged,
+    Quit,
+}
@@ -18,11 +18,15 @@ pub struct ShapeBuilder {
 
 impl ShapeBuilder {
     pub fn new(ctx: &mut Context, shape_color: ShapePalette) -> Self {
-        ShapeBuilder {
-            shapes: StationShape::iter()
-                .map(|t| ShapeBuilder::create_mesh(ctx, &shape_color, t))
-                .collect(),
-        }
+        let mut builder = ShapeBuilder { shapes: vec![] };
+        builder.set_palette(ctx, shape_color);
+        builder
+    }
+
+    pub fn set_palette(&mut self, ctx: &Context, shape_color: ShapePalette) {
+        self.shapes = StationShape::iter()
+            .map(|t| ShapeBuilder::create_mesh(ctx, &shape_color, t))
+            .collect();
     }
 
     pub fn get_mesh(&self, shape_type: StationShape) -> Shape {
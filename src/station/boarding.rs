@@ -0,0 +1,37 @@
+use crate::{passenger::Passenger, station::types::StationShape};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BoardingPolicy {
+    Fifo,
+    LongestWaitFirst,
+    DestinationMatchFirst,
+}
+
+impl BoardingPolicy {
+    pub fn next(&self) -> Self {
+        match self {
+            BoardingPolicy::Fifo => BoardingPolicy::LongestWaitFirst,
+            BoardingPolicy::LongestWaitFirst => BoardingPolicy::DestinationMatchFirst,
+            BoardingPolicy::DestinationMatchFirst => BoardingPolicy::Fifo,
+        }
+    }
+
+    // Passengers queue in arrival order per destination shape. `heads` holds the front of each
+    // of those queues, oldest first, and the policy only ever chooses between them. `served`
+    // lists the shapes the train still reaches in the order it reaches them; nobody boards a
+    // train that never gets them home.
+    pub fn select(&self, heads: &[(usize, Passenger)], served: &[StationShape]) -> Option<usize> {
+        let mut heads = heads.iter().filter(|(_, p)| served.contains(&p.kind()));
+        match self {
+            BoardingPolicy::Fifo => heads.next(),
+            BoardingPolicy::LongestWaitFirst => heads
+                .rev()
+                .max_by(|(_, a), (_, b)| a.wait_time().total_cmp(&b.wait_time())),
+            // Riders for the soonest stop free their seats first.
+            BoardingPolicy::DestinationMatchFirst => {
+                heads.min_by_key(|(_, p)| served.iter().position(|&kind| kind == p.kind()))
+            }
+        }
+        .map(|&(i, _)| i)
+    }
+}
@@ -0,0 +1,144 @@
+use rand::Rng;
+use rand_distr::{Distribution, Exp1};
+
+use crate::utils::day_cycle::DayCycle;
+
+use super::types::StationType;
+
+pub const MORNING_PEAK: f32 = 8.0;
+pub const EVENING_PEAK: f32 = 18.0;
+pub const PEAK_WIDTH: f32 = 1.5;
+pub const DEFAULT_SPAWN_RATE: f32 = 12.0;
+pub const DAILY_DEMAND_GROWTH: f32 = 0.1;
+
+fn peak(hour: f32, center: f32) -> f32 {
+    let distance = (hour - center) / PEAK_WIDTH;
+    (-0.5 * distance * distance).exp()
+}
+
+pub fn demand_factor(station_type: StationType, hour: f32) -> f32 {
+    let (base, morning, evening) = match station_type {
+        StationType::Normal => (1.0, 0.5, 0.5),
+        StationType::Residential => (0.6, 2.0, 0.4),
+        StationType::Commercial => (0.6, 0.4, 2.0),
+    };
+    let night = if (6.0..22.0).contains(&hour) {
+        1.0
+    } else {
+        0.4
+    };
+    (base + morning * peak(hour, MORNING_PEAK) + evening * peak(hour, EVENING_PEAK)) * night
+}
+
+// Arrivals per second for a station whose base rate is given in passengers per minute.
+pub fn spawn_rate(
+    passengers_per_minute: f32,
+    station_type: StationType,
+    day_cycle: &DayCycle,
+) -> f32 {
+    let growth = 1.0 + DAILY_DEMAND_GROWTH * (day_cycle.day() - 1) as f32;
+    passengers_per_minute / 60.0 * demand_factor(station_type, day_cycle.hour()) * growth
+}
+
+// A Poisson arrival process whose rate may change between steps.
+pub struct Arrivals {
+    next: f32,
+}
+
+impl Arrivals {
+    pub fn new() -> Self {
+        Arrivals { next: 0.0 }
+    }
+
+    // Inter-arrival times are unit exponentials measured in accumulated rate, which keeps
+    // the process Poisson while the rate changes over the day. Returns how many arrived.
+    pub fn advance(&mut self, rng: &mut impl Rng, rate: f32, delta: f32) -> usize {
+        self.next -= delta * rate;
+        let mut count = 0;
+        while self.next <= 0.0 {
+            count += 1;
+            self.next += Distribution::<f32>::sample(&Exp1, rng);
+        }
+        count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{SeedableRng, rngs::StdRng};
+
+    use super::*;
+    use crate::utils::day_cycle::DAY_LENGTH;
+
+    const STEP: f32 = 0.01;
+
+    fn arrival_times(rate: f32, count: usize) -> Vec<f32> {
+        let mut rng = StdRng::seed_from_u64(34);
+        let mut arrivals = Arrivals::new();
+        let mut times = vec![];
+        let mut step = 0;
+        while times.len() < count {
+            step += 1;
+            for _ in 0..arrivals.advance(&mut rng, rate, STEP) {
+                times.push(step as f32 * STEP);
+            }
+        }
+        times
+    }
+
+    #[test]
+    fn mean_inter_arrival_time_is_inverse_rate() {
+        let rate = 0.5;
+        let times = arrival_times(rate, 5000);
+        let mean = (times[times.len() - 1] - times[0]) / (times.len() - 1) as f32;
+        assert!((mean * rate - 1.0).abs() < 0.05, "mean gap {mean}");
+    }
+
+    #[test]
+    fn window_counts_have_variance_equal_to_mean() {
+        let rate = 0.5;
+        let steps_per_window = 1000;
+        let mut rng = StdRng::seed_from_u64(34);
+        let mut arrivals = Arrivals::new();
+        // The first passenger arrives as soon as the station opens.
+        arrivals.advance(&mut rng, rate, 0.0);
+        let counts: Vec<f32> = (0..2000)
+            .map(|_| {
+                (0..steps_per_window)
+                    .map(|_| arrivals.advance(&mut rng, rate, STEP))
+                    .sum::<usize>() as f32
+            })
+            .collect();
+        let expected = rate * STEP * steps_per_window as f32;
+        let mean = counts.iter().sum::<f32>() / counts.len() as f32;
+        let variance =
+            counts.iter().map(|c| (c - mean) * (c - mean)).sum::<f32>() / counts.len() as f32;
+        assert!((mean / expected - 1.0).abs() < 0.05, "mean {mean}");
+        assert!(
+            (variance / mean - 1.0).abs() < 0.1,
+            "variance {variance}, mean {mean}"
+        );
+    }
+
+    #[test]
+    fn spawn_rate_is_per_minute() {
+        let day_cycle = DayCycle::new(DAY_LENGTH);
+        let factor = demand_factor(StationType::Normal, day_cycle.hour());
+        let per_minute = 30.0;
+        let rate = spawn_rate(per_minute, StationType::Normal, &day_cycle) / factor;
+        assert!((rate * 60.0 - per_minute).abs() < 1e-4);
+
+        let mut rng = StdRng::seed_from_u64(34);
+        let mut arrivals = Arrivals::new();
+        let minutes = 200;
+        let steps = (60.0 / STEP) as usize * minutes;
+        let count: usize = (0..steps)
+            .map(|_| arrivals.advance(&mut rng, rate, STEP))
+            .sum();
+        let observed = count as f32 / minutes as f32;
+        assert!(
+            (observed / per_minute - 1.0).abs() < 0.05,
+            "{observed} per minute"
+        );
+    }
+}
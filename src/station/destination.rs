@@ -0,0 +1,109 @@
+use std::{collections::HashMap, fs, path::Path};
+
+use ggez::{GameError, GameResult};
+use strum::IntoEnumIterator;
+
+use super::{Station, types::StationShape};
+
+pub const GRAVITY_EXPONENT: f32 = 2.0;
+pub const GRAVITY_DISTANCE_OFFSET: f32 = 50.0;
+
+pub struct OdMatrix {
+    trips: HashMap<(usize, usize), f32>,
+}
+
+impl OdMatrix {
+    pub fn load(path: impl AsRef<Path>) -> GameResult<Self> {
+        let path = path.as_ref();
+        let source = fs::read_to_string(path)
+            .map_err(|e| GameError::ResourceLoadError(format!("{}: {}", path.display(), e)))?;
+        Self::parse(&source)
+    }
+
+    // One `origin,destination,weight` entry per line, `#` starts a comment.
+    pub fn parse(source: &str) -> GameResult<Self> {
+        let mut trips = HashMap::new();
+        for (number, line) in source.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            let entry = match fields.as_slice() {
+                [origin, destination, weight] => origin
+                    .parse::<usize>()
+                    .ok()
+                    .zip(destination.parse::<usize>().ok())
+                    .zip(weight.parse::<f32>().ok().filter(|w| *w >= 0.0)),
+                _ => None,
+            };
+            let Some(((origin, destination), weight)) = entry else {
+                return Err(GameError::ResourceLoadError(format!(
+                    "invalid OD matrix entry on line {}: {}",
+                    number + 1,
+                    line
+                )));
+            };
+            *trips.entry((origin, destination)).or_insert(0.0) += weight;
+        }
+        Ok(OdMatrix { trips })
+    }
+
+    // Entries for a removed station are dropped.
+    pub fn renumber(&mut self, renumber: impl Fn(usize) -> Option<usize>) {
+        self.trips = self
+            .trips
+            .drain()
+            .filter_map(|((origin, destination), weight)| {
+                Some(((renumber(origin)?, renumber(destination)?), weight))
+            })
+            .collect();
+    }
+
+    fn weight(&self, origin: usize, destination: usize) -> f32 {
+        self.trips
+            .get(&(origin, destination))
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+pub enum DestinationModel {
+    Gravity,
+    Matrix(OdMatrix),
+}
+
+impl DestinationModel {
+    pub fn renumber(&mut self, renumber: impl Fn(usize) -> Option<usize>) {
+        if let DestinationModel::Matrix(matrix) = self {
+            matrix.renumber(renumber);
+        }
+    }
+
+    pub fn weights(&self, origin: &Station, stations: &[Station]) -> Vec<(StationShape, f32)> {
+        StationShape::iter()
+            .filter(|&shape| shape != origin.kind())
+            .map(|shape| {
+                let destinations = stations.iter().filter(|s| s.kind() == shape);
+                let weight = match self {
+                    DestinationModel::Gravity => destinations
+                        .map(|s| {
+                            let distance = origin.position().distance(s.position());
+                            (distance, s.station_type().attractiveness())
+                        })
+                        .min_by(|a, b| a.0.total_cmp(&b.0))
+                        .map(|(distance, attractiveness)| {
+                            attractiveness
+                                / (distance + GRAVITY_DISTANCE_OFFSET).powf(GRAVITY_EXPONENT)
+                        })
+                        .unwrap_or_default(),
+                    DestinationModel::Matrix(matrix) => destinations
+                        .map(|s| matrix.weight(origin.id(), s.id()))
+                        .sum(),
+                };
+                (shape, weight)
+            })
+            .filter(|(_, weight)| *weight > 0.0)
+            .collect()
+    }
+}
@@ -1,5 +1,8 @@
 
+use std::{cmp::Ordering, path::Path};
+
 use ggez::{
+    Context, GameResult,
     glam::Vec2,
     graphics::Canvas,
 };
@@ -7,13 +10,29 @@ use rand::rngs::StdRng;
 
 use crate::{shape::{palette::ShapePalette, Shape, ShapeBuilder}, vehicle::handler::VehicleHandler};
 
-use super::{Station, types::StationShape};
+use crate::{
+    analytics::event::Event, passenger::routing::RoutingTable, utils::day_cycle::DayCycle,
+};
+
+use super::{
+    DEFAULT_CAPACITY, Station, StationSpec,
+    boarding::BoardingPolicy,
+    demand::DEFAULT_SPAWN_RATE,
+    destination::{DestinationModel, OdMatrix},
+    types::{StationShape, StationType},
+    walking::{DEFAULT_WALKING_RADIUS, WalkingLinks},
+};
 
 pub struct StationHandler {
     station_shapes: ShapeBuilder,
     passenger_shapes: ShapeBuilder,
-    available_shapes: Vec<StationShape>,
-    stations: Vec<Station>
+    stations: Vec<Station>,
+    destination_model: DestinationModel,
+    boarding_policy: BoardingPolicy,
+    walking_links: WalkingLinks,
+    revision: u32,
+    // Tallies of removed stations, so the totals never shrink.
+    retired: (u32, u32),
 }
 
 impl StationHandler {
@@ -21,32 +40,198 @@ impl StationHandler {
         StationHandler {
             station_shapes,
             passenger_shapes,
-            available_shapes: vec![],
-            stations: vec![]
+            stations: vec![],
+            destination_model: DestinationModel::Gravity,
+            boarding_policy: BoardingPolicy::Fifo,
+            walking_links: WalkingLinks::new(DEFAULT_WALKING_RADIUS),
+            revision: 0,
+            retired: (0, 0),
         }
     }
 
-    pub fn add_station(&mut self, kind: StationShape, position: Vec2) {
-        self.stations
-            .push(Station::new(self.stations.len(), kind, 1.0, position, 5.0, 10));
-        if !self.available_shapes.contains(&kind) {
-            self.available_shapes.push(kind);
+    pub fn set_palettes(&mut self, ctx: &Context, station: ShapePalette, passenger: ShapePalette) {
+        self.station_shapes.set_palette(ctx, station);
+        self.passenger_shapes.set_palette(ctx, passenger);
+    }
+
+    pub fn add_station(&mut self, kind: StationShape, station_type: StationType, position: Vec2) {
+        self.insert_station(
+            self.stations.len(),
+            StationSpec {
+                kind,
+                station_type,
+                position,
+                spawn_rate: DEFAULT_SPAWN_RATE,
+                capacity: DEFAULT_CAPACITY,
+            },
+        );
+    }
+
+    pub fn insert_station(&mut self, id: usize, spec: StationSpec) {
+        let mut station = Station::new(
+            id,
+            spec.kind,
+            spec.station_type,
+            1.0,
+            spec.position,
+            spec.spawn_rate,
+            spec.capacity,
+        );
+        station.set_boarding_policy(self.boarding_policy);
+        self.stations.insert(id, station);
+        self.renumber(|s| Some(if s >= id { s + 1 } else { s }));
+    }
+
+    // Riders still at the station are abandoned rather than silently dropped.
+    pub fn remove_station(&mut self, id: usize) -> StationSpec {
+        let mut station = self.stations.remove(id);
+        station.abandon_all();
+        self.retired.0 += station.delivered();
+        self.retired.1 += station.abandoned();
+        let spec = station.spec();
+        self.renumber(|s| match s.cmp(&id) {
+            Ordering::Less => Some(s),
+            Ordering::Equal => None,
+            Ordering::Greater => Some(s - 1),
+        });
+        spec
+    }
+
+    pub fn move_station(&mut self, id: usize, position: Vec2) {
+        self.stations[id].set_position(position);
+        self.refresh_layout();
+    }
+
+    fn renumber(&mut self, renumber: impl Fn(usize) -> Option<usize>) {
+        for (i, station) in self.stations.iter_mut().enumerate() {
+            station.set_id(i);
+            station.renumber_walkers(&renumber);
+        }
+        self.destination_model.renumber(&renumber);
+        self.refresh_layout();
+    }
+
+    fn refresh_layout(&mut self) {
+        self.set_walking_radius(self.walking_links.radius());
+        self.update_destinations();
+    }
+
+    pub fn load_od_matrix(&mut self, path: impl AsRef<Path>) -> GameResult {
+        self.destination_model = DestinationModel::Matrix(OdMatrix::load(path)?);
+        self.update_destinations();
+        Ok(())
+    }
+
+    fn update_destinations(&mut self) {
+        let destinations: Vec<_> = self
+            .stations
+            .iter()
+            .map(|station| self.destination_model.weights(station, &self.stations))
+            .collect();
+        for (station, destinations) in self.stations.iter_mut().zip(destinations) {
+            station.set_destinations(destinations);
+        }
+    }
+
+    pub fn walking_links(&self) -> &WalkingLinks {
+        &self.walking_links
+    }
+
+    pub fn set_walking_radius(&mut self, radius: f32) {
+        let positions: Vec<Vec2> = self.stations.iter().map(|s| s.position()).collect();
+        self.walking_links.update(&positions, radius);
+        self.revision = self.revision.wrapping_add(1);
+    }
+
+    pub fn set_lines(&mut self, lines: Vec<Vec<usize>>) {
+        if *self.walking_links.lines() != lines {
+            self.walking_links.set_lines(lines);
+            self.set_walking_radius(self.walking_links.radius());
         }
     }
 
+    // Bumped whenever stations or walking links change.
+    pub fn revision(&self) -> u32 {
+        self.revision
+    }
+
+    pub fn boarding_policy(&self) -> BoardingPolicy {
+        self.boarding_policy
+    }
+
+    pub fn set_boarding_policy(&mut self, boarding_policy: BoardingPolicy) {
+        self.boarding_policy = boarding_policy;
+        for station in self.stations.iter_mut() {
+            station.set_boarding_policy(boarding_policy);
+        }
+    }
+
+    pub fn delivered(&self) -> u32 {
+        self.retired.0
+            + self
+                .stations
+                .iter()
+                .map(|station| station.delivered())
+                .sum::<u32>()
+    }
+
+    pub fn abandoned(&self) -> u32 {
+        self.retired.1
+            + self
+                .stations
+                .iter()
+                .map(|station| station.abandoned())
+                .sum::<u32>()
+    }
+
+    pub fn is_overcrowded(&self) -> bool {
+        self.stations.iter().any(|station| station.is_overcrowded())
+    }
+
+    pub fn drain_events(&mut self) -> Vec<Event> {
+        self.stations
+            .iter_mut()
+            .flat_map(|station| station.drain_events())
+            .collect()
+    }
+
     pub fn stations(&self) -> &Vec<Station> {
         &self.stations
     }
 
+    pub fn station_at(&self, position: Vec2) -> Option<usize> {
+        self.stations
+            .iter()
+            .find(|s| s.position().distance(position) <= s.size())
+            .map(|s| s.id())
+    }
+
     pub fn draw(&mut self, canvas: &mut Canvas, vehicles: &VehicleHandler) {
         for station in self.stations.iter_mut() {
             station.draw(canvas, vehicles, &self.station_shapes, &self.passenger_shapes);
         }
     }
 
-    pub fn update(&mut self, rng: &mut StdRng, delta: f32) {
+    pub fn update(
+        &mut self,
+        rng: &mut StdRng,
+        routing: &RoutingTable,
+        day_cycle: &DayCycle,
+        delta: f32,
+    ) {
+        let positions: Vec<Vec2> = self.stations.iter().map(|s| s.position()).collect();
+        let mut walkers = vec![];
         for station in self.stations.iter_mut() {
-            station.update(rng, &self.available_shapes, delta);
+            walkers.extend(station.update(
+                rng,
+                routing,
+                &positions,
+                day_cycle,
+                delta,
+            ));
+        }
+        for (to, passenger, position) in walkers {
+            self.stations[to].accept_walker(passenger, position);
         }
     }
 
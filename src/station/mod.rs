@@ -1,24 +1,44 @@
 use std::f32::consts::{PI, TAU};
 
+use boarding::BoardingPolicy;
+use demand::{Arrivals, spawn_rate};
 use ggez::{
     glam::Vec2,
     graphics::{Canvas, DrawParam},
 };
 use lerp::Lerp;
 use rand::{Rng, rngs::StdRng, seq::IndexedRandom};
-use rand_distr::{Distribution, Poisson};
-use types::StationShape;
+use strum::IntoEnumIterator;
+use types::{StationShape, StationType};
+use walking::WALKING_SPEED;
 
 use crate::{
-    passenger::{Passenger, PassengerState},
+    analytics::{
+        Counters,
+        event::{Event, EventKind},
+    },
+    passenger::{
+        Passenger, PassengerState,
+        routing::{NextHop, RoutingTable},
+    },
+    route::flow::FLOW_WINDOW,
     shape::{Shape, ShapeBuilder},
+    utils::{AngleCalc, day_cycle::DayCycle},
     vehicle::{Vehicle, handler::VehicleHandler, metro::Metro},
 };
 
+pub mod boarding;
+pub mod demand;
+pub mod destination;
 pub mod handler;
 pub mod types;
+pub mod walking;
+
+pub const DEFAULT_CAPACITY: usize = 10;
+pub const OVERCROWD_LIMIT: f32 = 60.0;
 
 const MAX_PASSENGER_RADIUS: f32 = 10.0;
+const ABANDON_WALK_DISTANCE: f32 = 40.0;
 
 pub struct PassengerOnStation {
     passenger: Passenger,
@@ -27,23 +47,43 @@ pub struct PassengerOnStation {
     prev_passenger_position: Vec2,
 }
 
+#[derive(Clone, Copy)]
+pub struct StationSpec {
+    pub kind: StationShape,
+    pub station_type: StationType,
+    pub position: Vec2,
+    pub spawn_rate: f32,
+    pub capacity: usize,
+}
+
 pub struct Station {
     id: usize,
     kind: StationShape,
+    station_type: StationType,
     size: f32,
     position: Vec2,
-    next_spawn_distr: Poisson<f32>,
-    last_spawn_time: f32,
+    spawn_rate: f32,
+    arrivals: Arrivals,
+    destinations: Vec<(StationShape, f32)>,
 
     passengers: Vec<Passenger>,
     passenger_render_state: Vec<(f32, f32, Vec2)>,
     capacity: usize,
+    overcrowd_time: f32,
+    average_queue: f32,
+    boarding_policy: BoardingPolicy,
+
+    delivered: u32,
+    abandoned: u32,
+    counters: Counters,
+    events: Vec<Event>,
 }
 
 impl Station {
     pub fn new(
         id: usize,
         kind: StationShape,
+        station_type: StationType,
         size: f32,
         position: Vec2,
         passenger_spawn_rate: f32,
@@ -53,13 +93,22 @@ impl Station {
         Station {
             id,
             kind,
+            station_type,
             size,
             position,
-            next_spawn_distr: Poisson::new(passenger_spawn_rate).unwrap(),
+            spawn_rate: passenger_spawn_rate,
             passengers: vec![],
-            last_spawn_time: 0.0,
+            arrivals: Arrivals::new(),
+            destinations: vec![],
             capacity,
+            overcrowd_time: 0.0,
+            average_queue: 0.0,
             passenger_render_state: vec![],
+            boarding_policy: BoardingPolicy::Fifo,
+            delivered: 0,
+            abandoned: 0,
+            counters: Counters::default(),
+            events: vec![],
         }
     }
 
@@ -67,40 +116,258 @@ impl Station {
         self.id
     }
 
+    pub fn set_id(&mut self, id: usize) {
+        self.id = id;
+        for event in self.events.iter_mut() {
+            event.station = id;
+        }
+    }
+
+    pub fn spec(&self) -> StationSpec {
+        StationSpec {
+            kind: self.kind,
+            station_type: self.station_type,
+            position: self.position,
+            spawn_rate: self.spawn_rate,
+            capacity: self.capacity,
+        }
+    }
+
     pub fn kind(&self) -> StationShape {
         self.kind
     }
 
+    pub fn station_type(&self) -> StationType {
+        self.station_type
+    }
+
     pub fn position(&self) -> Vec2 {
         self.position
     }
 
+    pub fn set_position(&mut self, position: Vec2) {
+        self.position = position;
+    }
+
+    pub fn spawn_rate(&self) -> f32 {
+        self.spawn_rate
+    }
+
+    pub fn set_spawn_rate(&mut self, spawn_rate: f32) {
+        self.spawn_rate = spawn_rate;
+    }
+
     pub fn size(&self) -> f32 {
         self.size * 15.0
     }
 
     pub fn spawn_passenger(&mut self, kind: StationShape) {
+        self.record_event(EventKind::Spawn, None, kind);
         self.passengers.push(Passenger::new(kind));
         self.passenger_render_state.push((0.0, 0.0, self.position));
     }
 
-    pub fn try_take_vehicle(&mut self, vehicle: &mut dyn Vehicle) -> Vec<Passenger> {
-        if vehicle.available_spaces() == 0 || self.passengers.len() == 0 {
-            return vec![];
-        }
-        let mut moved_passengers: Vec<Passenger> = vec![];
-        for (i, passenger) in self.passengers.iter_mut().enumerate() {
-            if passenger.state() != PassengerState::OnStation {
-                continue;
+    pub fn set_destinations(&mut self, destinations: Vec<(StationShape, f32)>) {
+        self.destinations = destinations;
+    }
+
+    pub fn set_boarding_policy(&mut self, boarding_policy: BoardingPolicy) {
+        self.boarding_policy = boarding_policy;
+    }
+
+    pub fn waiting_count(&self) -> usize {
+        self.passengers
+            .iter()
+            .filter(|p| p.state() == PassengerState::OnStation)
+            .count()
+    }
+
+    pub fn crowding(&self) -> f32 {
+        self.waiting_count() as f32 / self.capacity.max(1) as f32
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+    }
+
+    pub fn overcrowd_time(&self) -> f32 {
+        self.overcrowd_time
+    }
+
+    pub fn is_overcrowded(&self) -> bool {
+        self.overcrowd_time > OVERCROWD_LIMIT
+    }
+
+    pub fn average_queue(&self) -> f32 {
+        self.average_queue
+    }
+
+    pub fn waiting_by_destination(&self) -> Vec<(StationShape, usize)> {
+        StationShape::iter()
+            .map(|shape| {
+                let count = self
+                    .passengers
+                    .iter()
+                    .filter(|p| p.state() == PassengerState::OnStation && p.kind() == shape)
+                    .count();
+                (shape, count)
+            })
+            .filter(|&(_, count)| count > 0)
+            .collect()
+    }
+
+    // The first waiting passenger for each destination shape, in arrival order.
+    fn queue_heads(&self) -> Vec<(usize, Passenger)> {
+        let mut heads: Vec<(usize, Passenger)> = vec![];
+        for (i, passenger) in self.passengers.iter().enumerate() {
+            if passenger.state() == PassengerState::OnStation
+                && !heads.iter().any(|(_, head)| head.kind() == passenger.kind())
+            {
+                heads.push((i, *passenger));
             }
-            moved_passengers.push(passenger.clone());
-            self.passenger_render_state[i] = (vehicle.position().distance(self.passenger_render_state[i].2), 0.025, self.passenger_render_state[i].2);
-            passenger.set_state(PassengerState::LeavingStation(
+        }
+        heads
+    }
+
+    pub fn try_take_vehicle(
+        &mut self,
+        vehicle: &dyn Vehicle,
+        served: &[StationShape],
+    ) -> Option<Passenger> {
+        if vehicle.available_spaces() == 0 {
+            return None;
+        }
+        let i = self.boarding_policy.select(&self.queue_heads(), served)?;
+        let passenger = self.passengers[i];
+        self.counters.record_boarding(&passenger);
+        self.record_event(EventKind::Board, Some(vehicle.id()), passenger.kind());
+        self.passenger_render_state[i] = (
+            vehicle
+                .position()
+                .distance(self.passenger_render_state[i].2),
+            0.025,
+            self.passenger_render_state[i].2,
+        );
+        self.passengers[i].set_state(PassengerState::LeavingStation(
+            vehicle.id(),
+            vehicle.position(),
+        ));
+        Some(passenger)
+    }
+
+    pub fn counters(&self) -> &Counters {
+        &self.counters
+    }
+
+    pub fn delivered(&self) -> u32 {
+        self.delivered
+    }
+
+    pub fn abandoned(&self) -> u32 {
+        self.abandoned
+    }
+
+    pub fn receive_passenger(&mut self, mut passenger: Passenger, vehicle: &dyn Vehicle) {
+        self.counters
+            .record_alighting(&passenger, passenger.kind() == self.kind);
+        self.record_event(EventKind::Alight, Some(vehicle.id()), passenger.kind());
+        if passenger.kind() == self.kind {
+            self.delivered += 1;
+            self.record_event(EventKind::Deliver, Some(vehicle.id()), passenger.kind());
+            passenger.set_state(PassengerState::LeavingVehicle(
                 vehicle.id(),
                 vehicle.position(),
             ));
+            self.passengers.push(passenger);
+            self.passenger_render_state.push((
+                vehicle.position().distance(self.position),
+                0.025,
+                vehicle.position(),
+            ));
+        } else {
+            self.passengers.push(passenger);
+            self.passenger_render_state
+                .push((0.0, 0.0, vehicle.position()));
+            let angle = (vehicle.position() - self.position).angle();
+            self.abandon_passenger(self.passengers.len() - 1, angle);
+        }
+    }
+
+    // Someone changing to a walking link here rather than riding on.
+    pub fn transfer_passenger(&mut self, passenger: Passenger, vehicle: &dyn Vehicle) {
+        self.counters.record_alighting(&passenger, false);
+        self.record_event(EventKind::Alight, Some(vehicle.id()), passenger.kind());
+        self.accept_walker(passenger, vehicle.position());
+    }
+
+    pub fn accept_walker(&mut self, mut passenger: Passenger, position: Vec2) {
+        passenger.set_state(PassengerState::OnStation);
+        self.passengers.push(passenger);
+        self.passenger_render_state.push((
+            (position - self.position).angle(),
+            position.distance(self.position),
+            position,
+        ));
+    }
+
+    // Follows station ids through an insertion or removal. Walkers whose target disappeared
+    // queue here again.
+    pub fn renumber_walkers(&mut self, renumber: impl Fn(usize) -> Option<usize>) {
+        for passenger in self.passengers.iter_mut() {
+            if let PassengerState::Walking(to, position) = passenger.state() {
+                passenger.set_state(match renumber(to) {
+                    Some(to) => PassengerState::Walking(to, position),
+                    None => PassengerState::OnStation,
+                });
+            }
+        }
+    }
+
+    pub fn drain_events(&mut self) -> Vec<Event> {
+        std::mem::take(&mut self.events)
+    }
+
+    fn record_event(
+        &mut self,
+        kind: EventKind,
+        vehicle: Option<usize>,
+        destination: StationShape,
+    ) {
+        self.events
+            .push(Event::new(kind, self.id, vehicle, destination));
+    }
+
+    // Everyone still waiting here or about to walk off gives up, as when the station closes.
+    pub fn abandon_all(&mut self) {
+        for i in 0..self.passengers.len() {
+            if let PassengerState::OnStation | PassengerState::Walking(..) =
+                self.passengers[i].state()
+            {
+                self.abandon_passenger(i, self.passenger_render_state[i].0);
+            }
+        }
+    }
+
+    fn abandon_passenger(&mut self, i: usize, angle: f32) {
+        self.abandoned += 1;
+        self.record_event(EventKind::Abandon, None, self.passengers[i].kind());
+        let target =
+            self.position + Vec2::from_angle(angle) * (self.size() + ABANDON_WALK_DISTANCE);
+        self.passengers[i].set_state(PassengerState::Abandoning(target));
+        self.passenger_render_state[i].0 = target.distance(self.passenger_render_state[i].2);
+    }
+
+    fn passenger_target(&self, state: PassengerState) -> Option<Vec2> {
+        match state {
+            PassengerState::LeavingStation(_, pos) => Some(pos),
+            PassengerState::LeavingVehicle(..) => Some(self.position),
+            PassengerState::Abandoning(pos) => Some(pos),
+            _ => None,
         }
-        return moved_passengers;
     }
 
     pub fn draw(
@@ -111,10 +378,7 @@ impl Station {
         passenger_shapes: &ShapeBuilder,
     ) {
         for (i, passenger) in self.passengers.iter().enumerate() {
-            if !matches!(
-                self.passengers[i].state(),
-                PassengerState::LeavingStation(..)
-            ) {
+            if self.passengers[i].state() == PassengerState::OnStation {
                 self.passenger_render_state[i].0 = self.passenger_render_state[i].0
                     .lerp(TAU * i as f32 / self.passengers.len() as f32, 0.05);
                 self.passenger_render_state[i].1 =
@@ -137,7 +401,14 @@ impl Station {
                 .dest(self.position),
         );
         for (i, passenger) in self.passengers.iter().enumerate() {
-            if let PassengerState::LeavingStation(_, pos) = self.passengers[i].state() {
+            if let PassengerState::Walking(..) = self.passengers[i].state() {
+                passenger_shapes.get_mesh(passenger.kind()).draw(
+                    canvas,
+                    DrawParam::default()
+                        .scale([0.2, 0.2])
+                        .dest(self.passenger_render_state[i].2),
+                );
+            } else if let Some(pos) = self.passenger_target(self.passengers[i].state()) {
                 self.passenger_render_state[i].2 = self.passenger_render_state[i].2.lerp(pos, 0.07);
                 
                 let scale = ((pos.distance(self.passenger_render_state[i].2) - 0.05) / self.passenger_render_state[i].0) * 0.2 + 0.05;
@@ -151,15 +422,56 @@ impl Station {
         }
     }
 
-    fn update(&mut self, rng: &mut StdRng, available_shapes: &Vec<StationShape>, delta: f32) {
-        self.last_spawn_time -= delta;
-        if self.last_spawn_time <= 0.0 {
-            self.spawn_passenger(available_shapes.choose(rng).unwrap().clone());
-            self.last_spawn_time = self.next_spawn_distr.sample(rng);
+    fn update(
+        &mut self,
+        rng: &mut StdRng,
+        routing: &RoutingTable,
+        station_positions: &[Vec2],
+        day_cycle: &DayCycle,
+        delta: f32,
+    ) -> Vec<(usize, Passenger, Vec2)> {
+        let rate = spawn_rate(self.spawn_rate, self.station_type, day_cycle);
+        for _ in 0..self.arrivals.advance(rng, rate, delta) {
+            if let Ok(&(kind, _)) = self.destinations.choose_weighted(rng, |d| d.1) {
+                self.spawn_passenger(kind);
+            }
+        }
+        let waiting = self.waiting_count() as f32;
+        self.average_queue += (waiting - self.average_queue) * (delta / FLOW_WINDOW).min(1.0);
+        if self.waiting_count() > self.capacity {
+            self.overcrowd_time += delta;
+        } else {
+            self.overcrowd_time = (self.overcrowd_time - delta).max(0.0);
+        }
+        for i in 0..self.passengers.len() {
+            if self.passengers[i].state() != PassengerState::OnStation {
+                continue;
+            }
+            self.passengers[i].wait(delta);
+            if self.passengers[i].is_out_of_patience() {
+                self.abandon_passenger(i, self.passenger_render_state[i].0);
+            } else if let Some(NextHop::Walk(to)) =
+                routing.next_hop(self.id, self.passengers[i].kind())
+            {
+                self.passengers[i].set_state(PassengerState::Walking(to, station_positions[to]));
+            }
+        }
+
+        let mut walkers = vec![];
+        for i in (0..self.passengers.len()).rev() {
+            if let PassengerState::Walking(to, pos) = self.passengers[i].state() {
+                self.passengers[i].wait(delta);
+                let offset = pos - self.passenger_render_state[i].2;
+                self.passenger_render_state[i].2 += offset.clamp_length_max(WALKING_SPEED * delta);
+                if pos.distance(self.passenger_render_state[i].2) < self.size() {
+                    let (_, _, position) = self.passenger_render_state.remove(i);
+                    walkers.push((to, self.passengers.remove(i), position));
+                }
+            }
         }
         if !self.passengers.is_empty() {
             for i in (0..self.passengers.len()).rev() {
-                if let PassengerState::LeavingStation(_, pos) = self.passengers[i].state() {
+                if let Some(pos) = self.passenger_target(self.passengers[i].state()) {
                     if pos.distance(self.passenger_render_state[i].2) < 0.05 {
                         self.passengers.remove(i);
                         self.passenger_render_state.remove(i);
@@ -167,5 +479,6 @@ impl Station {
                 }
             }
         }
+        walkers
     }
 }
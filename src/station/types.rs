@@ -8,5 +8,24 @@ pub enum StationShape {
 
 #[derive(strum_macros::EnumIter, strum_macros::EnumCount, Clone, Copy, Debug, PartialEq, Eq)]
 pub enum StationType {
-    Normal = 0
-}
\ No newline at end of file
+    Normal = 0,
+    Residential = 1,
+    Commercial = 2,
+}
+
+impl StationType {
+    pub fn boarding_time_factor(&self) -> f32 {
+        match self {
+            StationType::Normal | StationType::Residential => 1.0,
+            StationType::Commercial => 0.8,
+        }
+    }
+
+    pub fn attractiveness(&self) -> f32 {
+        match self {
+            StationType::Normal => 1.0,
+            StationType::Residential => 0.6,
+            StationType::Commercial => 2.0,
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use ggez::glam::Vec2;
+
+pub const DEFAULT_WALKING_RADIUS: f32 = 100.0;
+// Zero turns walking transfers off.
+pub const WALKING_RADII: [f32; 4] = [0.0, DEFAULT_WALKING_RADIUS, 150.0, 200.0];
+pub const WALKING_SPEED: f32 = 20.0;
+pub const TRANSFER_PENALTY: f32 = 5.0;
+
+pub struct WalkingLinks {
+    radius: f32,
+    lines: Vec<Vec<usize>>,
+    links: Vec<(usize, usize, f32)>,
+}
+
+impl WalkingLinks {
+    pub fn new(radius: f32) -> Self {
+        WalkingLinks {
+            radius,
+            lines: vec![],
+            links: vec![],
+        }
+    }
+
+    pub fn radius(&self) -> f32 {
+        self.radius
+    }
+
+    pub fn links(&self) -> &Vec<(usize, usize, f32)> {
+        &self.links
+    }
+
+    pub fn lines(&self) -> &Vec<Vec<usize>> {
+        &self.lines
+    }
+
+    // The stations on each line. Stations that already share a line get no walking link.
+    pub fn set_lines(&mut self, lines: Vec<Vec<usize>>) {
+        self.lines = lines;
+    }
+
+    fn share_line(&self, a: usize, b: usize) -> bool {
+        self.lines
+            .iter()
+            .any(|line| line.contains(&a) && line.contains(&b))
+    }
+
+    pub fn update(&mut self, positions: &[Vec2], radius: f32) {
+        self.radius = radius;
+        self.links.clear();
+        for (a, pos_a) in positions.iter().enumerate() {
+            for (b, pos_b) in positions.iter().enumerate().skip(a + 1) {
+                let distance = pos_a.distance(*pos_b);
+                if distance <= self.radius && !self.share_line(a, b) {
+                    self.links.push((a, b, distance));
+                }
+            }
+        }
+    }
+
+    pub fn walking_time(distance: f32) -> f32 {
+        distance / WALKING_SPEED + TRANSFER_PENALTY
+    }
+}
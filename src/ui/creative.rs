@@ -0,0 +1,138 @@
+use ggez::{
+    Context,
+    glam::Vec2,
+    graphics::{Canvas, Rect},
+    input::keyboard::KeyCode,
+};
+use strum::IntoEnumIterator;
+
+use crate::{
+    depot::handler::DepotHandler,
+    edit::Command,
+    station::{
+        DEFAULT_CAPACITY, StationSpec,
+        demand::DEFAULT_SPAWN_RATE,
+        handler::StationHandler,
+        types::{StationShape, StationType},
+    },
+};
+
+use super::{PANEL_PADDING, draw_panel, measure_panel};
+
+const SPAWN_RATE_STEP: f32 = 2.0;
+
+pub struct CreativePanel {
+    shape: StationShape,
+    station_type: StationType,
+}
+
+impl CreativePanel {
+    pub fn new() -> Self {
+        CreativePanel {
+            shape: StationShape::Circle,
+            station_type: StationType::Normal,
+        }
+    }
+
+    pub fn handle_key(
+        &mut self,
+        keycode: KeyCode,
+        cursor: Vec2,
+        selected: Option<usize>,
+        stations: &StationHandler,
+        depots: &DepotHandler,
+    ) -> Option<Command> {
+        let shape_keys = [KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4];
+        let type_keys = [KeyCode::Key5, KeyCode::Key6, KeyCode::Key7];
+        if let Some(i) = shape_keys.iter().position(|&key| key == keycode) {
+            self.shape = StationShape::iter().nth(i).unwrap_or(self.shape);
+            return None;
+        }
+        if let Some(i) = type_keys.iter().position(|&key| key == keycode) {
+            self.station_type = StationType::iter().nth(i).unwrap_or(self.station_type);
+            return None;
+        }
+        match keycode {
+            KeyCode::Q => Some(Command::PlaceStation {
+                station: stations.stations().len(),
+                spec: StationSpec {
+                    kind: self.shape,
+                    station_type: self.station_type,
+                    position: cursor,
+                    spawn_rate: DEFAULT_SPAWN_RATE,
+                    capacity: DEFAULT_CAPACITY,
+                },
+            }),
+            KeyCode::D => Some(Command::PlaceDepot {
+                depot: depots.depots().len(),
+                position: cursor,
+            }),
+            KeyCode::X => match stations.station_at(cursor) {
+                Some(station) => Some(Command::RemoveStation { station }),
+                None => depots
+                    .depot_at(cursor)
+                    .map(|depot| Command::RemoveDepot { depot }),
+            },
+            KeyCode::Equals | KeyCode::Minus => {
+                let station = selected?;
+                let step = if keycode == KeyCode::Equals {
+                    SPAWN_RATE_STEP
+                } else {
+                    -SPAWN_RATE_STEP
+                };
+                Some(Command::SetSpawnRate {
+                    station,
+                    spawn_rate: (stations.get(station).spawn_rate() + step).max(SPAWN_RATE_STEP),
+                })
+            }
+            KeyCode::Period | KeyCode::Comma => {
+                let station = selected?;
+                let capacity = stations.get(station).capacity();
+                Some(Command::SetCapacity {
+                    station,
+                    capacity: if keycode == KeyCode::Period {
+                        capacity + 1
+                    } else {
+                        capacity.saturating_sub(1).max(1)
+                    },
+                })
+            }
+            _ => None,
+        }
+    }
+
+    pub fn draw(
+        &self,
+        ctx: &Context,
+        canvas: &mut Canvas,
+        viewport: Rect,
+        selected: Option<usize>,
+        stations: &StationHandler,
+    ) {
+        let mut lines = vec![
+            String::from("Creative mode"),
+            format!("Brush {:?} / {:?}", self.shape, self.station_type),
+            String::from("[1-4] shape  [5-7] type  [Q] place  [X] delete"),
+            String::from("[D] place depot  [Ctrl+drag] move station"),
+        ];
+        if let Some(station) = selected {
+            let station = stations.get(station);
+            lines.push(format!(
+                "St. {}   spawn {:.0}/min [-/=]   capacity {} [,/.]",
+                station.id(),
+                station.spawn_rate(),
+                station.capacity()
+            ));
+        }
+        let size = measure_panel(ctx, &lines);
+        draw_panel(
+            ctx,
+            canvas,
+            Vec2::new(
+                viewport.x + PANEL_PADDING,
+                viewport.center().y - size.y / 2.0,
+            ),
+            &lines,
+        );
+    }
+}
@@ -0,0 +1,175 @@
+use ggez::{
+    Context,
+    glam::Vec2,
+    graphics::{Canvas, Color, DrawMode, DrawParam, Mesh, MeshBuilder, Rect, Text},
+    input::keyboard::KeyCode,
+};
+
+use crate::{
+    route::{Route, handler::RouteHandler, segment::VehicleState},
+    utils::colors::Colors,
+    vehicle::handler::VehicleHandler,
+};
+
+use super::{PANEL_PADDING, draw_panel, measure_panel};
+
+const DEBUG_TEXT_SIZE: f32 = 10.0;
+const POINT_RADIUS: f32 = 2.5;
+const TURNING_POINT_RADIUS: f32 = 5.0;
+const DEBUG_LINE_WIDTH: f32 = 1.0;
+const TICK_SMOOTHING: f32 = 0.1;
+
+pub struct DebugOverlay {
+    visible: bool,
+    tick_time: f32,
+}
+
+impl DebugOverlay {
+    pub fn new() -> Self {
+        DebugOverlay {
+            visible: false,
+            tick_time: 0.0,
+        }
+    }
+
+    pub fn handle_key(&mut self, keycode: KeyCode) -> bool {
+        if keycode == KeyCode::F3 {
+            self.visible = !self.visible;
+            return true;
+        }
+        false
+    }
+
+    pub fn record_tick(&mut self, seconds: f32) {
+        self.tick_time += (seconds - self.tick_time) * TICK_SMOOTHING;
+    }
+
+    pub fn draw(
+        &self,
+        ctx: &Context,
+        canvas: &mut Canvas,
+        viewport: Rect,
+        routes: &RouteHandler,
+        vehicles: &VehicleHandler,
+    ) {
+        if !self.visible {
+            return;
+        }
+        let mut mb = MeshBuilder::new();
+        for route in routes.iter() {
+            build_route_geometry(&mut mb, canvas, route);
+        }
+        for vehicle in vehicles.vehicles() {
+            draw_label(
+                canvas,
+                vehicle.position() + Vec2::new(8.0, 8.0),
+                &format!(
+                    "#{} seg {} d {:.1} dir {:+}",
+                    vehicle.id(),
+                    vehicle.segment(),
+                    vehicle.distance(),
+                    vehicle.direction()
+                ),
+            );
+        }
+        canvas.draw(&Mesh::from_data(ctx, mb.build()), DrawParam::default());
+
+        let lines = vec![format!(
+            "FPS {:.0}   tick {:.2} ms",
+            ctx.time.fps(),
+            self.tick_time * 1000.0
+        )];
+        let size = measure_panel(ctx, &lines);
+        draw_panel(
+            ctx,
+            canvas,
+            Vec2::new(
+                viewport.right() - size.x - PANEL_PADDING,
+                viewport.bottom() - size.y - PANEL_PADDING,
+            ),
+            &lines,
+        );
+    }
+}
+
+fn draw_label(canvas: &mut Canvas, position: Vec2, label: &str) {
+    let mut text = Text::new(label);
+    text.set_scale(DEBUG_TEXT_SIZE);
+    canvas.draw(
+        &text,
+        DrawParam::default().dest(position).color(Colors::text()),
+    );
+}
+
+fn point(mb: &mut MeshBuilder, position: Vec2, radius: f32, mode: DrawMode, color: Color) {
+    mb.circle(mode, position, radius, 0.1, color)
+        .expect("Error creating debug mesh");
+}
+
+fn ray(mb: &mut MeshBuilder, center: Vec2, angle: f32, radius: f32, color: Color) {
+    mb.line(
+        &[center, center + Vec2::from_angle(angle) * radius],
+        DEBUG_LINE_WIDTH,
+        color,
+    )
+    .expect("Error creating debug mesh");
+}
+
+fn build_route_geometry(mb: &mut MeshBuilder, canvas: &mut Canvas, route: &Route) {
+    let (begin_color, end_color) = (Color::GREEN, Color::RED);
+    for i in 0..route.length() {
+        let segment = route.get(i);
+        point(
+            mb,
+            segment.begin_pos(),
+            POINT_RADIUS,
+            DrawMode::fill(),
+            begin_color,
+        );
+        point(
+            mb,
+            segment.end_pos(),
+            POINT_RADIUS,
+            DrawMode::fill(),
+            end_color,
+        );
+
+        match segment.state() {
+            VehicleState::ArrivePlatform(center, radius, entrance, middle, exit, _)
+            | VehicleState::LeavePlatform(center, radius, entrance, middle, exit, _) => {
+                point(mb, center, POINT_RADIUS, DrawMode::fill(), Color::BLUE);
+                ray(mb, center, entrance, radius, begin_color);
+                ray(mb, center, middle, radius, Color::BLUE);
+                ray(mb, center, exit, radius, end_color);
+            }
+            VehicleState::Moving => {
+                let next = route.get((i + 1) % route.length());
+                if next.state() == VehicleState::Moving && next.begin_pos() == segment.end_pos() {
+                    point(
+                        mb,
+                        segment.end_pos(),
+                        TURNING_POINT_RADIUS,
+                        DrawMode::stroke(DEBUG_LINE_WIDTH),
+                        Color::MAGENTA,
+                    );
+                }
+            }
+            VehicleState::Curve(control) => {
+                point(
+                    mb,
+                    control,
+                    POINT_RADIUS,
+                    DrawMode::stroke(DEBUG_LINE_WIDTH),
+                    Color::BLUE,
+                );
+            }
+            VehicleState::LastPlatform(_) => {}
+        }
+
+        draw_label(
+            canvas,
+            segment.calculate_position(segment.length() / 2.0),
+            &format!("{}:{} {}", route.id(), i, segment.state().name()),
+        );
+    }
+}
@@ -0,0 +1,225 @@
+use ggez::{
+    Context,
+    glam::Vec2,
+    graphics::{Canvas, DrawParam, Mesh, MeshBuilder, Rect},
+};
+
+use crate::{
+    edit::{Command, EditError, Resource, Resources, handler::EditHistory, mode::GameMode},
+    route::{ROUTE_LINE_WIDTH, handler::RouteHandler, stop::Stop},
+    station::handler::StationHandler,
+    utils::colors::Colors,
+    vehicle::handler::VehicleHandler,
+};
+
+use super::{PANEL_PADDING, draw_panel, inspector::VEHICLE_HIT_RADIUS, measure_panel};
+
+#[derive(Clone, Copy)]
+enum Drag {
+    Station(usize),
+    Vehicle(usize),
+    MoveStation(usize, Vec2),
+}
+
+pub struct RouteEditor {
+    drag: Option<Drag>,
+    start: Vec2,
+    cursor: Vec2,
+    status: Option<String>,
+}
+
+impl RouteEditor {
+    pub fn new() -> Self {
+        RouteEditor {
+            drag: None,
+            start: Vec2::ZERO,
+            cursor: Vec2::ZERO,
+            status: None,
+        }
+    }
+
+    pub fn begin_drag(
+        &mut self,
+        position: Vec2,
+        move_station: bool,
+        stations: &StationHandler,
+        vehicles: &VehicleHandler,
+    ) {
+        self.start = position;
+        self.cursor = position;
+        self.drag = stations
+            .station_at(position)
+            .map(|station| {
+                if move_station {
+                    Drag::MoveStation(station, stations.get(station).position())
+                } else {
+                    self.start = stations.get(station).position();
+                    Drag::Station(station)
+                }
+            })
+            .or_else(|| {
+                vehicles
+                    .vehicles()
+                    .iter()
+                    .find(|v| v.position().distance(position) <= VEHICLE_HIT_RADIUS)
+                    .map(|v| Drag::Vehicle(v.id()))
+            });
+    }
+
+    pub fn cursor(&self) -> Vec2 {
+        self.cursor
+    }
+
+    // Returns the station being moved and where it should go now.
+    pub fn move_cursor(&mut self, position: Vec2) -> Option<(usize, Vec2)> {
+        self.cursor = position;
+        match self.drag {
+            Some(Drag::MoveStation(station, _)) => Some((station, position)),
+            _ => None,
+        }
+    }
+
+    // Dropping a train on a station moves it to a line serving that station. Dragging from a
+    // terminus extends its line, with `insert` the target is added after the dragged stop, and
+    // anything else starts a new line.
+    pub fn end_drag(
+        &mut self,
+        position: Vec2,
+        insert: bool,
+        stations: &StationHandler,
+        routes: &RouteHandler,
+    ) -> Option<Command> {
+        let drag = self.drag.take()?;
+        // The station has followed the cursor as far as it was allowed to, so the move ends
+        // wherever it stopped.
+        if let Drag::MoveStation(station, from) = drag {
+            return Some(Command::MoveStation {
+                station,
+                from,
+                to: stations.get(station).position(),
+            });
+        }
+        let target = stations.station_at(position)?;
+        match drag {
+            Drag::Vehicle(vehicle) => routes
+                .iter()
+                .find(|route| route.stops().iter().any(|stop| stop.index() == target))
+                .map(|route| Command::MoveVehicle {
+                    vehicle,
+                    route: route.id(),
+                    station: target,
+                }),
+            Drag::Station(from) if from == target => None,
+            Drag::Station(from) if insert => routes.iter().find_map(|route| {
+                let position = route.stops().iter().position(|stop| stop.index() == from)?;
+                Some(Command::InsertStop {
+                    route: route.id(),
+                    position: position + 1,
+                    stop: Stop::auto(target),
+                })
+            }),
+            Drag::Station(from) => Some(
+                routes
+                    .iter()
+                    .find_map(|route| {
+                        route.terminus(from).map(|at_start| Command::ExtendRoute {
+                            route: route.id(),
+                            stop: Stop::auto(target),
+                            at_start,
+                        })
+                    })
+                    .unwrap_or(Command::CreateRoute {
+                        stops: vec![Stop::auto(from), Stop::auto(target)],
+                        is_looped: false,
+                    }),
+            ),
+            Drag::MoveStation(..) => None,
+        }
+    }
+
+    // A terminus shortens its line, any other stop is taken out of the first line serving it.
+    pub fn remove_at(
+        &self,
+        position: Vec2,
+        stations: &StationHandler,
+        routes: &RouteHandler,
+    ) -> Option<Command> {
+        let station = stations.station_at(position)?;
+        routes.iter().find_map(|route| {
+            if let Some(at_start) = route.terminus(station) {
+                return Some(Command::ShortenRoute {
+                    route: route.id(),
+                    at_start,
+                });
+            }
+            let position = route
+                .stops()
+                .iter()
+                .position(|stop| stop.index() == station)?;
+            Some(Command::RemoveStop {
+                route: route.id(),
+                position,
+            })
+        })
+    }
+
+    pub fn set_status(&mut self, result: Result<(), EditError>) {
+        self.status = result.err().map(|e| e.description());
+    }
+
+    pub fn draw(
+        &self,
+        ctx: &Context,
+        canvas: &mut Canvas,
+        viewport: Rect,
+        resources: &Resources,
+        mode: GameMode,
+        history: &EditHistory,
+    ) {
+        if let Some(Drag::Station(_)) = self.drag
+            && self.start.distance(self.cursor) > 1.0
+        {
+            let mut mb = MeshBuilder::new();
+            mb.line(&[self.start, self.cursor], ROUTE_LINE_WIDTH, Colors::text())
+                .expect("Error creating preview mesh");
+            canvas.draw(&Mesh::from_data(ctx, mb.build()), DrawParam::default());
+        }
+
+        let available = if mode.has_unlimited_resources() {
+            String::from("Unlimited lines, trains and carriages")
+        } else {
+            format!(
+                "Lines {}   Trains {}   Carriages {}",
+                resources.available(Resource::Line),
+                resources.available(Resource::Train),
+                resources.available(Resource::Carriage)
+            )
+        };
+        let mut lines = vec![format!(
+            "{}   {}{}{}",
+            mode.name(),
+            available,
+            if history.can_undo() {
+                "   [Ctrl+Z] undo"
+            } else {
+                ""
+            },
+            if history.can_redo() {
+                "   [Ctrl+Y] redo"
+            } else {
+                ""
+            },
+        )];
+        lines.extend(self.status.clone());
+        let size = measure_panel(ctx, &lines);
+        draw_panel(
+            ctx,
+            canvas,
+            Vec2::new(
+                viewport.center().x - size.x / 2.0,
+                viewport.bottom() - size.y - PANEL_PADDING,
+            ),
+            &lines,
+        );
+    }
+}
@@ -0,0 +1,76 @@
+use ggez::{
+    Context,
+    graphics::{Canvas, DrawMode, DrawParam, Mesh, MeshBuilder},
+    input::keyboard::KeyCode,
+};
+
+use crate::{
+    route::{ROUTE_LINE_WIDTH, handler::RouteHandler},
+    station::handler::StationHandler,
+    utils::colors::Colors,
+};
+
+const MIN_FLOW_WIDTH: f32 = 2.0;
+const MAX_FLOW_WIDTH: f32 = ROUTE_LINE_WIDTH * 4.0;
+const QUEUE_RING_WIDTH: f32 = 4.0;
+
+pub struct Heatmap {
+    visible: bool,
+}
+
+impl Heatmap {
+    pub fn new() -> Self {
+        Heatmap { visible: false }
+    }
+
+    pub fn handle_key(&mut self, keycode: KeyCode) -> bool {
+        if keycode == KeyCode::F {
+            self.visible = !self.visible;
+            return true;
+        }
+        false
+    }
+
+    pub fn draw(
+        &self,
+        ctx: &Context,
+        canvas: &mut Canvas,
+        routes: &RouteHandler,
+        stations: &StationHandler,
+    ) {
+        if !self.visible {
+            return;
+        }
+        let max_flow = routes
+            .iter()
+            .flat_map(|route| (0..route.length()).map(|i| route.flow().flow(i)))
+            .max()
+            .unwrap_or_default()
+            .max(1) as f32;
+
+        let mut mb = MeshBuilder::new();
+        for route in routes.iter() {
+            for i in 0..route.length() {
+                let t = route.flow().flow(i) as f32 / max_flow;
+                route.build_segment(
+                    &mut mb,
+                    route.get(i),
+                    Colors::heat(t),
+                    MIN_FLOW_WIDTH + (MAX_FLOW_WIDTH - MIN_FLOW_WIDTH) * t,
+                );
+            }
+        }
+        for station in stations.stations() {
+            let t = station.average_queue() / station.capacity().max(1) as f32;
+            mb.circle(
+                DrawMode::stroke(QUEUE_RING_WIDTH),
+                station.position(),
+                station.size() + QUEUE_RING_WIDTH,
+                0.1,
+                Colors::heat(t),
+            )
+            .expect("Error creating heatmap mesh");
+        }
+        canvas.draw(&Mesh::from_data(ctx, mb.build()), DrawParam::default());
+    }
+}
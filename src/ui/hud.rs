@@ -0,0 +1,50 @@
+use ggez::{
+    Context,
+    glam::Vec2,
+    graphics::{Canvas, Rect},
+};
+
+use crate::{
+    edit::mode::GameMode, passenger::satisfaction::Satisfaction, utils::day_cycle::DayCycle,
+};
+
+use super::{PANEL_PADDING, draw_panel, measure_panel};
+
+pub fn draw_hud(
+    ctx: &Context,
+    canvas: &mut Canvas,
+    viewport: Rect,
+    satisfaction: &Satisfaction,
+    day_cycle: &DayCycle,
+    mode: GameMode,
+    hard_mode: bool,
+) {
+    let lines = if mode.has_game_over() {
+        vec![format!(
+            "{}   {}   Satisfaction {:.0}%   delivered {}   abandoned {}{}",
+            day_cycle.clock_label(),
+            mode.name(),
+            satisfaction.score() * 100.0,
+            satisfaction.delivered(),
+            satisfaction.abandoned(),
+            if hard_mode { "   [hard]" } else { "" }
+        )]
+    } else {
+        vec![format!(
+            "{}   {}   delivered {}",
+            day_cycle.clock_label(),
+            mode.name(),
+            satisfaction.delivered()
+        )]
+    };
+    let size = measure_panel(ctx, &lines);
+    draw_panel(
+        ctx,
+        canvas,
+        Vec2::new(
+            viewport.right() - size.x - PANEL_PADDING,
+            viewport.y + PANEL_PADDING,
+        ),
+        &lines,
+    );
+}
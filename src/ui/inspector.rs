@@ -0,0 +1,174 @@
+use ggez::{
+    Context,
+    glam::Vec2,
+    graphics::{Canvas, Rect},
+};
+use strum::IntoEnumIterator;
+
+use crate::{
+    route::handler::RouteHandler,
+    station::{handler::StationHandler, types::StationShape},
+    vehicle::handler::VehicleHandler,
+};
+
+use super::{PANEL_PADDING, draw_panel, measure_panel};
+
+pub const VEHICLE_HIT_RADIUS: f32 = 15.0;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Selection {
+    Station(usize),
+    Vehicle(usize),
+}
+
+pub struct Inspector {
+    selection: Option<Selection>,
+}
+
+impl Inspector {
+    pub fn new() -> Self {
+        Inspector { selection: None }
+    }
+
+    pub fn select(&mut self, position: Vec2, stations: &StationHandler, vehicles: &VehicleHandler) {
+        let station = stations.station_at(position).map(Selection::Station);
+        let vehicle = || {
+            vehicles
+                .vehicles()
+                .iter()
+                .find(|v| v.position().distance(position) <= VEHICLE_HIT_RADIUS)
+                .map(|v| Selection::Vehicle(v.id()))
+        };
+        self.selection = station.or_else(vehicle);
+    }
+
+    pub fn selection(&self) -> Option<Selection> {
+        self.selection
+    }
+
+    pub fn selected_station(&self, stations: &StationHandler) -> Option<usize> {
+        match self.selection {
+            Some(Selection::Station(id)) if id < stations.stations().len() => Some(id),
+            _ => None,
+        }
+    }
+
+    pub fn draw(
+        &self,
+        ctx: &Context,
+        canvas: &mut Canvas,
+        viewport: Rect,
+        stations: &StationHandler,
+        routes: &RouteHandler,
+        vehicles: &VehicleHandler,
+    ) {
+        let lines = match self.selection {
+            Some(Selection::Station(id)) if id < stations.stations().len() => {
+                station_lines(id, stations, routes)
+            }
+            Some(Selection::Vehicle(id)) if id < vehicles.vehicles().len() => {
+                vehicle_lines(id, routes, vehicles)
+            }
+            _ => return,
+        };
+        let size = measure_panel(ctx, &lines);
+        draw_panel(
+            ctx,
+            canvas,
+            Vec2::new(
+                viewport.x + PANEL_PADDING,
+                viewport.bottom() - size.y - PANEL_PADDING,
+            ),
+            &lines,
+        );
+    }
+}
+
+fn destination_line(counts: &[(StationShape, usize)]) -> String {
+    if counts.is_empty() {
+        return String::from("  none");
+    }
+    counts
+        .iter()
+        .map(|(shape, count)| format!("  {:?} {}", shape, count))
+        .collect::<Vec<_>>()
+        .join("")
+}
+
+fn station_lines(id: usize, stations: &StationHandler, routes: &RouteHandler) -> Vec<String> {
+    let station = stations.get(id);
+    let lines_served: Vec<String> = routes
+        .iter()
+        .filter(|route| route.stops().iter().any(|stop| stop.index() == id))
+        .map(|route| format!("Line {}", route.id() + 1))
+        .collect();
+    vec![
+        format!(
+            "Station {}   {:?} / {:?}",
+            id,
+            station.kind(),
+            station.station_type()
+        ),
+        format!("Waiting {}/{}", station.waiting_count(), station.capacity()),
+        destination_line(&station.waiting_by_destination()),
+        format!("Overcrowded {:.1}s", station.overcrowd_time()),
+        format!(
+            "Lines {}",
+            if lines_served.is_empty() {
+                String::from("none")
+            } else {
+                lines_served.join(", ")
+            }
+        ),
+    ]
+}
+
+fn vehicle_lines(id: usize, routes: &RouteHandler, vehicles: &VehicleHandler) -> Vec<String> {
+    let vehicle = vehicles.get(id);
+    let occupancy: Vec<(StationShape, usize)> = StationShape::iter()
+        .map(|shape| {
+            let count = vehicle
+                .passengers()
+                .iter()
+                .filter(|p| p.kind() == shape)
+                .count();
+            (shape, count)
+        })
+        .filter(|&(_, count)| count > 0)
+        .collect();
+    let status = if vehicle.is_broken_down() {
+        "broken down"
+    } else if vehicle.is_blocked() {
+        "blocked"
+    } else if vehicle.is_stopping() {
+        "at platform"
+    } else {
+        "moving"
+    };
+    vec![
+        format!(
+            "Train {}   Line {}   {}",
+            id,
+            vehicle.route() + 1,
+            if vehicle.direction() > 0.0 {
+                "outbound"
+            } else {
+                "inbound"
+            }
+        ),
+        format!("Speed {:.0}   {}", vehicle.speed(), status),
+        format!(
+            "Occupancy {}/{}",
+            vehicle.passengers().len(),
+            vehicle.capacity()
+        ),
+        destination_line(&occupancy),
+        format!(
+            "Next stop {}",
+            vehicle
+                .next_stop(routes)
+                .map(|station| format!("St. {}", station))
+                .unwrap_or_else(|| String::from("-"))
+        ),
+    ]
+}
@@ -0,0 +1,63 @@
+use ggez::{
+    Context,
+    glam::Vec2,
+    graphics::{Canvas, Rect},
+    input::keyboard::KeyCode,
+};
+
+use super::{draw_panel, measure_panel};
+
+pub enum MenuAction {
+    Select(usize),
+    Adjust(usize),
+    Back,
+}
+
+pub struct Menu {
+    selected: usize,
+}
+
+impl Menu {
+    pub fn new() -> Self {
+        Menu { selected: 0 }
+    }
+
+    pub fn handle_key(&mut self, keycode: KeyCode, count: usize) -> Option<MenuAction> {
+        if count == 0 {
+            return (keycode == KeyCode::Escape).then_some(MenuAction::Back);
+        }
+        self.selected = self.selected.min(count - 1);
+        match keycode {
+            KeyCode::Up => self.selected = (self.selected + count - 1) % count,
+            KeyCode::Down | KeyCode::Tab => self.selected = (self.selected + 1) % count,
+            KeyCode::Return | KeyCode::Space => return Some(MenuAction::Select(self.selected)),
+            KeyCode::Left | KeyCode::Right => return Some(MenuAction::Adjust(self.selected)),
+            KeyCode::Escape => return Some(MenuAction::Back),
+            _ => {}
+        }
+        None
+    }
+
+    pub fn draw(
+        &self,
+        ctx: &Context,
+        canvas: &mut Canvas,
+        viewport: Rect,
+        header: &[String],
+        items: &[String],
+    ) {
+        let mut lines = header.to_vec();
+        lines.push(String::new());
+        for (i, item) in items.iter().enumerate() {
+            let marker = if i == self.selected { ">" } else { " " };
+            lines.push(format!("{marker} {item}"));
+        }
+        let size = measure_panel(ctx, &lines);
+        draw_panel(
+            ctx,
+            canvas,
+            Vec2::new(viewport.center().x, viewport.center().y) - size / 2.0,
+            &lines,
+        );
+    }
+}
@@ -0,0 +1,54 @@
+use ggez::{
+    Context,
+    glam::Vec2,
+    graphics::{Canvas, DrawParam, Quad, Text},
+};
+
+use crate::utils::colors::Colors;
+
+pub mod creative;
+pub mod debug;
+pub mod editor;
+pub mod heatmap;
+pub mod hud;
+pub mod inspector;
+pub mod menu;
+pub mod stats;
+pub mod timetable;
+
+pub const PANEL_PADDING: f32 = 8.0;
+pub const PANEL_TEXT_SIZE: f32 = 14.0;
+
+fn panel_text(lines: &[String]) -> Text {
+    let mut text = Text::new(lines.join("\n"));
+    text.set_scale(PANEL_TEXT_SIZE);
+    text
+}
+
+pub fn measure_panel(ctx: &Context, lines: &[String]) -> Vec2 {
+    panel_text(lines)
+        .measure(ctx)
+        .map(Vec2::from)
+        .unwrap_or_default()
+        + Vec2::splat(PANEL_PADDING * 2.0)
+}
+
+pub fn draw_panel(ctx: &Context, canvas: &mut Canvas, position: Vec2, lines: &[String]) -> Vec2 {
+    let text = panel_text(lines);
+    let size = measure_panel(ctx, lines);
+
+    canvas.draw(
+        &Quad,
+        DrawParam::default()
+            .dest(position)
+            .scale(size)
+            .color(Colors::panel()),
+    );
+    canvas.draw(
+        &text,
+        DrawParam::default()
+            .dest(position + Vec2::splat(PANEL_PADDING))
+            .color(Colors::text()),
+    );
+    size
+}
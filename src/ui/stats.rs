@@ -0,0 +1,278 @@
+use ggez::{
+    Context,
+    glam::Vec2,
+    graphics::{Canvas, Color, DrawMode, DrawParam, Mesh, MeshBuilder, Quad, Rect, Text},
+    input::keyboard::KeyCode,
+};
+
+use crate::{
+    analytics::{Sample, Subject, handler::Analytics},
+    utils::colors::Colors,
+};
+
+use super::{PANEL_PADDING, PANEL_TEXT_SIZE};
+
+const CHART_GAP: f32 = 24.0;
+const CHART_LINE_WIDTH: f32 = 2.0;
+const AXIS_LINE_WIDTH: f32 = 1.0;
+
+pub struct StatsScreen {
+    subject: usize,
+}
+
+impl StatsScreen {
+    pub fn new() -> Self {
+        StatsScreen { subject: 0 }
+    }
+
+    pub fn handle_key(&mut self, keycode: KeyCode, analytics: &Analytics) -> bool {
+        let subject_count = analytics.subjects().len();
+        if subject_count == 0 {
+            return false;
+        }
+        match keycode {
+            KeyCode::Right | KeyCode::Tab => self.subject = (self.subject + 1) % subject_count,
+            KeyCode::Left => self.subject = (self.subject + subject_count - 1) % subject_count,
+            _ => return false,
+        }
+        true
+    }
+
+    pub fn draw(&self, ctx: &Context, canvas: &mut Canvas, viewport: Rect, analytics: &Analytics) {
+        let area = Rect::new(
+            viewport.x + PANEL_PADDING,
+            viewport.y + PANEL_PADDING,
+            viewport.w - PANEL_PADDING * 2.0,
+            viewport.h - PANEL_PADDING * 2.0,
+        );
+        canvas.draw(
+            &Quad,
+            DrawParam::default()
+                .dest(area.point())
+                .scale(area.size())
+                .color(Colors::panel()),
+        );
+
+        let subjects = analytics.subjects();
+        let subject = subjects
+            .get(self.subject.min(subjects.len().saturating_sub(1)))
+            .copied()
+            .unwrap_or(Subject::Network);
+        let totals = analytics.totals(subject);
+        let samples = analytics.series(subject);
+        let window = match (samples.first(), samples.last()) {
+            (Some(first), Some(last)) => last.time - first.time,
+            _ => 0.0,
+        };
+        draw_label(
+            canvas,
+            Vec2::new(area.x, area.y) + Vec2::splat(PANEL_PADDING),
+            &format!(
+                "Statistics: {}   boarded {}   alighted {}   avg wait {:.1}s   avg trip {:.1}s   last {:.0}s   [G] close  [Left/Right] subject",
+                subject_label(subject),
+                totals.boarded(),
+                totals.alighted(),
+                totals.average_wait(),
+                totals.average_trip_time(),
+                window
+            ),
+        );
+
+        let top = area.y + PANEL_PADDING * 2.0 + PANEL_TEXT_SIZE + CHART_GAP;
+        let column = (area.w - CHART_GAP * 3.0) / 2.0;
+        let row = (area.bottom() - top - CHART_GAP * 3.0) / 3.0;
+        let left = area.x + CHART_GAP;
+        let right = left + column + CHART_GAP;
+        let cell = |x: f32, index: usize| {
+            Rect::new(x, top + index as f32 * (row + CHART_GAP), column, row)
+        };
+
+        let palette = Colors::line_palette();
+        let series = |value: fn(&Sample) -> f32| samples.iter().map(value).collect::<Vec<f32>>();
+        draw_line_chart(
+            ctx,
+            canvas,
+            cell(left, 0),
+            "Boarded / alighted per interval",
+            &[
+                (series(|s| s.boarded as f32), palette[4]),
+                (series(|s| s.alighted as f32), palette[0]),
+            ],
+        );
+        draw_line_chart(
+            ctx,
+            canvas,
+            cell(left, 1),
+            "Average wait / trip time (s)",
+            &[
+                (series(|s| s.average_wait), palette[1]),
+                (series(|s| s.average_trip_time), palette[5]),
+            ],
+        );
+        draw_line_chart(
+            ctx,
+            canvas,
+            cell(left, 2),
+            "Load factor",
+            &[(series(|s| s.load_factor), palette[3])],
+        );
+
+        let bars = |filter: fn(&Subject) -> bool| {
+            subjects
+                .iter()
+                .filter(|subject| filter(subject))
+                .map(|&subject| {
+                    (
+                        subject_label(subject),
+                        analytics.totals(subject).boarded() as f32,
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        draw_bar_chart(
+            ctx,
+            canvas,
+            cell(right, 0),
+            "Boardings per line",
+            &bars(|s| matches!(s, Subject::Route(_))),
+            palette[4],
+        );
+        draw_bar_chart(
+            ctx,
+            canvas,
+            cell(right, 1),
+            "Boardings per station",
+            &bars(|s| matches!(s, Subject::Station(_))),
+            palette[1],
+        );
+        draw_bar_chart(
+            ctx,
+            canvas,
+            cell(right, 2),
+            "Boardings per train",
+            &bars(|s| matches!(s, Subject::Vehicle(_))),
+            palette[3],
+        );
+    }
+}
+
+fn subject_label(subject: Subject) -> String {
+    match subject {
+        Subject::Network => String::from("Network"),
+        Subject::Route(id) => format!("Line {}", id + 1),
+        Subject::Station(id) => format!("St. {}", id),
+        Subject::Vehicle(id) => format!("Train {}", id),
+    }
+}
+
+fn draw_label(canvas: &mut Canvas, position: Vec2, label: &str) {
+    let mut text = Text::new(label);
+    text.set_scale(PANEL_TEXT_SIZE);
+    canvas.draw(
+        &text,
+        DrawParam::default().dest(position).color(Colors::text()),
+    );
+}
+
+fn draw_axes(mb: &mut MeshBuilder, rect: Rect) {
+    mb.line(
+        &[
+            Vec2::new(rect.x, rect.y),
+            Vec2::new(rect.x, rect.bottom()),
+            Vec2::new(rect.right(), rect.bottom()),
+        ],
+        AXIS_LINE_WIDTH,
+        Colors::text(),
+    )
+    .expect("Error creating chart mesh");
+}
+
+fn draw_line_chart(
+    ctx: &Context,
+    canvas: &mut Canvas,
+    rect: Rect,
+    title: &str,
+    series: &[(Vec<f32>, Color)],
+) {
+    let max = series
+        .iter()
+        .flat_map(|(values, _)| values.iter().copied())
+        .fold(0.0, f32::max)
+        .max(f32::EPSILON);
+    draw_label(
+        canvas,
+        Vec2::new(rect.x, rect.y - PANEL_TEXT_SIZE - 2.0),
+        &format!("{}   max {:.2}", title, max),
+    );
+
+    let mut mb = MeshBuilder::new();
+    draw_axes(&mut mb, rect);
+    for (values, color) in series {
+        if values.len() < 2 {
+            continue;
+        }
+        let step = rect.w / (values.len() - 1) as f32;
+        let points: Vec<Vec2> = values
+            .iter()
+            .enumerate()
+            .map(|(i, value)| {
+                Vec2::new(
+                    rect.x + i as f32 * step,
+                    rect.bottom() - value / max * rect.h,
+                )
+            })
+            .collect();
+        mb.line(&points, CHART_LINE_WIDTH, *color)
+            .expect("Error creating chart mesh");
+    }
+    canvas.draw(&Mesh::from_data(ctx, mb.build()), DrawParam::default());
+}
+
+fn draw_bar_chart(
+    ctx: &Context,
+    canvas: &mut Canvas,
+    rect: Rect,
+    title: &str,
+    bars: &[(String, f32)],
+    color: Color,
+) {
+    draw_label(
+        canvas,
+        Vec2::new(rect.x, rect.y - PANEL_TEXT_SIZE - 2.0),
+        title,
+    );
+    let max = bars
+        .iter()
+        .map(|(_, value)| *value)
+        .fold(0.0, f32::max)
+        .max(f32::EPSILON);
+
+    let mut mb = MeshBuilder::new();
+    draw_axes(&mut mb, rect);
+    let label_height = PANEL_TEXT_SIZE + 2.0;
+    let plot_height = rect.h - label_height;
+    let width = rect.w / bars.len().max(1) as f32;
+    for (i, (label, value)) in bars.iter().enumerate() {
+        let height = value / max * plot_height;
+        let x = rect.x + i as f32 * width;
+        if height > 0.0 {
+            mb.rectangle(
+                DrawMode::fill(),
+                Rect::new(
+                    x + width * 0.15,
+                    rect.bottom() - height,
+                    width * 0.7,
+                    height,
+                ),
+                color,
+            )
+            .expect("Error creating chart mesh");
+        }
+        draw_label(
+            canvas,
+            Vec2::new(x + width * 0.15, rect.bottom() - height - label_height),
+            &format!("{} {:.0}", label, value),
+        );
+    }
+    canvas.draw(&Mesh::from_data(ctx, mb.build()), DrawParam::default());
+}
@@ -0,0 +1,135 @@
+use ggez::{
+    Context,
+    glam::Vec2,
+    graphics::{Canvas, Rect},
+    input::keyboard::KeyCode,
+};
+
+use crate::route::handler::RouteHandler;
+
+use super::{PANEL_PADDING, draw_panel};
+
+const DEPARTURE_STEP: f32 = 5.0;
+const PERIOD_STEP: f32 = 10.0;
+
+pub struct TimetableEditor {
+    visible: bool,
+    route: usize,
+    selected: usize,
+}
+
+impl TimetableEditor {
+    pub fn new() -> Self {
+        TimetableEditor {
+            visible: false,
+            route: 0,
+            selected: 0,
+        }
+    }
+
+    pub fn handle_key(&mut self, keycode: KeyCode, routes: &mut RouteHandler) -> bool {
+        if keycode == KeyCode::T {
+            self.visible = !self.visible;
+            return true;
+        }
+        let route_count = routes.iter().len();
+        if !self.visible || route_count == 0 {
+            return false;
+        }
+        self.route = self.route.min(route_count - 1);
+
+        let timetable = routes.get_mut(self.route).timetable_mut();
+        match keycode {
+            KeyCode::Tab => {
+                self.route = (self.route + 1) % route_count;
+                self.selected = 0;
+            }
+            KeyCode::S => timetable.set_enabled(!timetable.is_enabled()),
+            KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down => {
+                self.selected = (self.selected + 1).min(timetable.departures().len().max(1) - 1)
+            }
+            KeyCode::Left => {
+                self.selected = timetable.shift_departure(self.selected, -DEPARTURE_STEP)
+            }
+            KeyCode::Right => {
+                self.selected = timetable.shift_departure(self.selected, DEPARTURE_STEP)
+            }
+            KeyCode::N => {
+                let time = timetable
+                    .departures()
+                    .get(self.selected)
+                    .map_or(0.0, |d| d + DEPARTURE_STEP);
+                self.selected = timetable.add_departure(time);
+            }
+            KeyCode::Delete | KeyCode::Back => {
+                timetable.remove_departure(self.selected);
+                self.selected = self
+                    .selected
+                    .min(timetable.departures().len().saturating_sub(1));
+            }
+            KeyCode::LBracket => timetable.set_period(timetable.period() - PERIOD_STEP),
+            KeyCode::RBracket => timetable.set_period(timetable.period() + PERIOD_STEP),
+            _ => return false,
+        }
+        true
+    }
+
+    pub fn draw(&self, ctx: &Context, canvas: &mut Canvas, viewport: Rect, routes: &RouteHandler) {
+        if !self.visible || routes.iter().len() == 0 {
+            return;
+        }
+        let route = routes.get(self.route.min(routes.iter().len() - 1));
+        let timetable = route.timetable();
+
+        let mut lines = vec![
+            format!(
+                "Line {} timetable [{}]  clock {:.1}s",
+                route.id() + 1,
+                if timetable.is_enabled() {
+                    "scheduled"
+                } else {
+                    "unscheduled"
+                },
+                timetable.clock()
+            ),
+            format!(
+                "Every {:.0}s, departures from first stop:",
+                timetable.period()
+            ),
+        ];
+        for (i, departure) in timetable.departures().iter().enumerate() {
+            lines.push(format!(
+                "{} {:>6.1}s",
+                if i == self.selected { ">" } else { " " },
+                departure
+            ));
+        }
+        lines.push(String::from("Stop      sched  early  on  late   mean"));
+        for (call, &(station, distance)) in route.calls().iter().enumerate() {
+            let adherence = timetable.adherence(call);
+            lines.push(format!(
+                "St. {:<4} +{:>5.1}s {:>5} {:>3} {:>5} {:>+6.1}s",
+                station,
+                timetable.scheduled_offset(call, distance),
+                adherence.early(),
+                adherence.on_time(),
+                adherence.late(),
+                adherence.mean_deviation()
+            ));
+        }
+        lines.push(String::from(
+            "[T] close  [Tab] line  [S] scheduled  [Up/Down] select",
+        ));
+        lines.push(String::from(
+            "[Left/Right] shift  [N] add  [Del] remove  [ / ] period",
+        ));
+
+        draw_panel(
+            ctx,
+            canvas,
+            Vec2::new(viewport.x, viewport.y) + Vec2::splat(PANEL_PADDING),
+            &lines,
+        );
+    }
+}
@@ -1,21 +1,151 @@
+use std::sync::{PoisonError, RwLock, RwLockReadGuard};
+
 use ggez::graphics::Color;
+use lazy_static::lazy_static;
+
+use super::theme::{Theme, ThemeColors};
+
+lazy_static! {
+    static ref THEME: RwLock<ThemeColors> = RwLock::new(Theme::Light.load());
+}
+
+#[derive(strum_macros::EnumIter, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LinePalette {
+    Theme,
+    OkabeIto,
+    TolBright,
+}
+
+impl LinePalette {
+    pub fn name(&self) -> &'static str {
+        match self {
+            LinePalette::Theme => "theme",
+            LinePalette::OkabeIto => "okabe-ito",
+            LinePalette::TolBright => "tol-bright",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            LinePalette::Theme => LinePalette::OkabeIto,
+            LinePalette::OkabeIto => LinePalette::TolBright,
+            LinePalette::TolBright => LinePalette::Theme,
+        }
+    }
+
+    // The Okabe-Ito and Tol palettes stay distinct under the common forms of colour blindness.
+    pub fn colors(&self) -> Vec<Color> {
+        match self {
+            LinePalette::Theme => Colors::line_palette(),
+            LinePalette::OkabeIto => vec![
+                Color::from_rgb(230, 159, 0),
+                Color::from_rgb(86, 180, 233),
+                Color::from_rgb(0, 158, 115),
+                Color::from_rgb(240, 228, 66),
+                Color::from_rgb(0, 114, 178),
+                Color::from_rgb(213, 94, 0),
+                Color::from_rgb(204, 121, 167),
+            ],
+            LinePalette::TolBright => vec![
+                Color::from_rgb(68, 119, 170),
+                Color::from_rgb(238, 102, 119),
+                Color::from_rgb(34, 136, 51),
+                Color::from_rgb(204, 187, 68),
+                Color::from_rgb(102, 204, 238),
+                Color::from_rgb(170, 51, 119),
+                Color::from_rgb(187, 187, 187),
+            ],
+        }
+    }
+}
 
 pub struct Colors {}
 
 impl Colors {
-    pub fn background() -> Color {
-        Color::from_rgb(237, 237, 235)
-    }
-
-    pub fn default_palette() -> Vec<Color> {
-        vec![
-            Color::from_rgb(235, 64, 52),
-            Color::from_rgb(235, 167, 59),
-            Color::from_rgb(232, 222, 30),
-            Color::from_rgb(34, 240, 123),
-            Color::from_rgb(18, 159, 219),
-            Color::from_rgb(30, 19, 235),
-            Color::from_rgb(184, 12, 232),
-        ]
-    }
-}
\ No newline at end of file
+    pub fn set_theme(colors: ThemeColors) {
+        *THEME.write().unwrap_or_else(PoisonError::into_inner) = colors;
+    }
+
+    fn theme() -> RwLockReadGuard<'static, ThemeColors> {
+        THEME.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn background(daylight: f32) -> Color {
+        let theme = Self::theme();
+        let (night, day) = (theme.background_night, theme.background_day);
+        Color::new(
+            night.r + (day.r - night.r) * daylight,
+            night.g + (day.g - night.g) * daylight,
+            night.b + (day.b - night.b) * daylight,
+            1.0,
+        )
+    }
+
+    pub fn panel() -> Color {
+        Self::theme().panel
+    }
+
+    pub fn text() -> Color {
+        Self::theme().text
+    }
+
+    pub fn station_fill() -> Color {
+        Self::theme().station_fill
+    }
+
+    pub fn station_outline() -> Color {
+        Self::theme().station_outline
+    }
+
+    pub fn passenger() -> Color {
+        Self::theme().passenger
+    }
+
+    pub fn cargo_fill() -> Color {
+        Self::theme().cargo_fill
+    }
+
+    pub fn cargo_outline() -> Color {
+        Self::theme().cargo_outline
+    }
+
+    pub fn depot_fill() -> Color {
+        Self::theme().depot_fill
+    }
+
+    pub fn depot_outline() -> Color {
+        Self::theme().depot_outline
+    }
+
+    pub fn vehicle(line: Color) -> Color {
+        let tint = Self::theme().vehicle_tint;
+        Color::new(line.r * tint.r, line.g * tint.g, line.b * tint.b, line.a)
+    }
+
+    pub fn heat(t: f32) -> Color {
+        let t = t.clamp(0.0, 1.0);
+        let (from, to, t) = if t < 0.5 {
+            (
+                Color::from_rgb(46, 204, 113),
+                Color::from_rgb(241, 196, 15),
+                t * 2.0,
+            )
+        } else {
+            (
+                Color::from_rgb(241, 196, 15),
+                Color::from_rgb(231, 76, 60),
+                t * 2.0 - 1.0,
+            )
+        };
+        Color::new(
+            from.r + (to.r - from.r) * t,
+            from.g + (to.g - from.g) * t,
+            from.b + (to.b - from.b) * t,
+            1.0,
+        )
+    }
+
+    pub fn line_palette() -> Vec<Color> {
+        Self::theme().lines.clone()
+    }
+}
@@ -0,0 +1,241 @@
+use std::{fmt, fs, path::PathBuf};
+
+use directories::ProjectDirs;
+use ggez::{GameError, GameResult, conf::NumSamples};
+use strum::IntoEnumIterator;
+
+use crate::{
+    route::{LineMarking, LineStyle},
+    station::walking::{DEFAULT_WALKING_RADIUS, WALKING_RADII},
+};
+
+use super::{colors::LinePalette, theme::Theme};
+
+pub const SETTINGS_FILE: &str = "settings.cfg";
+pub const MIN_WINDOW_SIZE: (f32, f32) = (640.0, 360.0);
+pub const MAX_WINDOW_SIZE: (f32, f32) = (1920.0, 1080.0);
+pub const WINDOW_SIZES: [(f32, f32); 4] = [
+    (960.0, 540.0),
+    (1280.0, 720.0),
+    (1600.0, 900.0),
+    (1920.0, 1080.0),
+];
+pub const GAME_SPEEDS: [f32; 3] = [1.0, 2.0, 4.0];
+
+pub fn config_dir() -> Option<PathBuf> {
+    ProjectDirs::from("", "waltsai", "rusty-metro").map(|dirs| dirs.config_dir().to_path_buf())
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Settings {
+    pub window_width: f32,
+    pub window_height: f32,
+    pub samples: NumSamples,
+    pub theme: Theme,
+    pub game_speed: f32,
+    pub line_style: LineStyle,
+    pub line_palette: LinePalette,
+    pub line_marking: LineMarking,
+    pub line_labels: bool,
+    pub walking_radius: f32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            window_width: WINDOW_SIZES[1].0,
+            window_height: WINDOW_SIZES[1].1,
+            samples: NumSamples::Four,
+            theme: Theme::Light,
+            game_speed: GAME_SPEEDS[0],
+            line_style: LineStyle::Octilinear,
+            line_palette: LinePalette::Theme,
+            line_marking: LineMarking::Colour,
+            line_labels: false,
+            walking_radius: DEFAULT_WALKING_RADIUS,
+        }
+    }
+}
+
+impl Settings {
+    pub fn path() -> Option<PathBuf> {
+        config_dir().map(|dir| dir.join(SETTINGS_FILE))
+    }
+
+    // A missing file means first launch. Broken lines are reported and left at their defaults,
+    // then replaced on the next save.
+    pub fn load() -> Self {
+        let Some(path) = Self::path() else {
+            return Settings::default();
+        };
+        let Ok(source) = fs::read_to_string(&path) else {
+            return Settings::default();
+        };
+        let (settings, errors) = Self::parse(&source);
+        for e in errors {
+            eprintln!("Ignoring setting in {}: {e}", path.display());
+        }
+        settings
+    }
+
+    // One `key = value` entry per line, `#` starts a comment. Unknown keys are skipped, invalid
+    // lines are skipped and returned alongside the settings parsed from the rest.
+    pub fn parse(source: &str) -> (Self, Vec<GameError>) {
+        let mut settings = Settings::default();
+        let mut errors = vec![];
+        for (number, line) in source.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let parsed = line
+                .split_once('=')
+                .map(|(key, value)| (key.trim(), value.trim()))
+                .and_then(|(key, value)| settings.set(key, value));
+            if parsed.is_none() {
+                errors.push(GameError::ConfigError(format!(
+                    "invalid setting on line {}: {}",
+                    number + 1,
+                    line
+                )));
+            }
+        }
+        (settings, errors)
+    }
+
+    fn set(&mut self, key: &str, value: &str) -> Option<()> {
+        match key {
+            "window_width" => {
+                self.window_width = value
+                    .parse::<f32>()
+                    .ok()?
+                    .clamp(MIN_WINDOW_SIZE.0, MAX_WINDOW_SIZE.0)
+            }
+            "window_height" => {
+                self.window_height = value
+                    .parse::<f32>()
+                    .ok()?
+                    .clamp(MIN_WINDOW_SIZE.1, MAX_WINDOW_SIZE.1)
+            }
+            "samples" => self.samples = NumSamples::try_from(value.parse::<u8>().ok()?).ok()?,
+            "theme" => self.theme = Theme::iter().find(|theme| theme.name() == value)?,
+            "game_speed" => self.game_speed = value.parse::<f32>().ok().filter(|s| *s > 0.0)?,
+            "line_style" => {
+                self.line_style = LineStyle::iter().find(|style| style.name() == value)?
+            }
+            "line_palette" => {
+                self.line_palette = LinePalette::iter().find(|palette| palette.name() == value)?
+            }
+            "line_marking" => {
+                self.line_marking = LineMarking::iter().find(|marking| marking.name() == value)?
+            }
+            "line_labels" => self.line_labels = value.parse().ok()?,
+            "walking_radius" => {
+                self.walking_radius = value.parse::<f32>().ok().filter(|r| *r >= 0.0)?
+            }
+            _ => {}
+        }
+        Some(())
+    }
+
+    pub fn save(&self) -> GameResult {
+        let path = Self::path()
+            .ok_or_else(|| GameError::FilesystemError("no user config directory".to_string()))?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&path, self.to_string())?;
+        Ok(())
+    }
+
+    pub fn next_window_size(&mut self) {
+        let current = WINDOW_SIZES
+            .iter()
+            .position(|&(w, _)| w > self.window_width - 1.0)
+            .unwrap_or(WINDOW_SIZES.len() - 1);
+        (self.window_width, self.window_height) = WINDOW_SIZES[(current + 1) % WINDOW_SIZES.len()];
+    }
+
+    pub fn next_samples(&mut self) {
+        self.samples = match self.samples {
+            NumSamples::One => NumSamples::Four,
+            NumSamples::Four => NumSamples::One,
+        };
+    }
+
+    pub fn next_game_speed(&mut self) {
+        let current = GAME_SPEEDS
+            .iter()
+            .position(|&speed| speed >= self.game_speed)
+            .unwrap_or(GAME_SPEEDS.len() - 1);
+        self.game_speed = GAME_SPEEDS[(current + 1) % GAME_SPEEDS.len()];
+    }
+
+    pub fn next_walking_radius(&mut self) {
+        let current = WALKING_RADII
+            .iter()
+            .position(|&radius| radius >= self.walking_radius)
+            .unwrap_or(WALKING_RADII.len() - 1);
+        self.walking_radius = WALKING_RADII[(current + 1) % WALKING_RADII.len()];
+    }
+}
+
+impl fmt::Display for Settings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "window_width = {}", self.window_width)?;
+        writeln!(f, "window_height = {}", self.window_height)?;
+        writeln!(f, "samples = {}", u8::from(self.samples))?;
+        writeln!(f, "theme = {}", self.theme.name())?;
+        writeln!(f, "game_speed = {}", self.game_speed)?;
+        writeln!(f, "line_style = {}", self.line_style.name())?;
+        writeln!(f, "line_palette = {}", self.line_palette.name())?;
+        writeln!(f, "line_marking = {}", self.line_marking.name())?;
+        writeln!(f, "line_labels = {}", self.line_labels)?;
+        writeln!(f, "walking_radius = {}", self.walking_radius)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_value_keeps_default_and_is_reported() {
+        let (settings, errors) = Settings::parse("game_speed = fast\nline_labels = true\n");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(settings.game_speed, Settings::default().game_speed);
+        assert!(settings.line_labels);
+    }
+
+    #[test]
+    fn unknown_key_is_skipped() {
+        let (settings, errors) = Settings::parse("volume = 80\ngame_speed = 2\n");
+        assert!(errors.is_empty());
+        assert_eq!(settings.game_speed, 2.0);
+    }
+
+    #[test]
+    fn comments_are_ignored() {
+        let source = "# saved by an older build\ngame_speed = 4 # fastest\n\n";
+        let (settings, errors) = Settings::parse(source);
+        assert!(errors.is_empty());
+        assert_eq!(settings.game_speed, 4.0);
+    }
+
+    #[test]
+    fn display_round_trips() {
+        let mut settings = Settings::default();
+        settings.next_window_size();
+        settings.next_samples();
+        settings.theme = settings.theme.next();
+        settings.next_game_speed();
+        settings.line_style = settings.line_style.next();
+        settings.line_palette = settings.line_palette.next();
+        settings.line_marking = settings.line_marking.next();
+        settings.line_labels = true;
+        settings.next_walking_radius();
+        let (parsed, errors) = Settings::parse(&settings.to_string());
+        assert!(errors.is_empty());
+        assert_eq!(parsed.to_string(), settings.to_string());
+    }
+}
@@ -0,0 +1,44 @@
+pub const DAY_LENGTH: f32 = 240.0;
+pub const HOURS_PER_DAY: f32 = 24.0;
+pub const STARTING_HOUR: f32 = 6.0;
+
+pub struct DayCycle {
+    elapsed: f32,
+    day_length: f32,
+}
+
+impl DayCycle {
+    pub fn new(day_length: f32) -> Self {
+        DayCycle {
+            elapsed: STARTING_HOUR / HOURS_PER_DAY * day_length,
+            day_length,
+        }
+    }
+
+    pub fn update(&mut self, delta: f32) {
+        self.elapsed += delta;
+    }
+
+    pub fn day(&self) -> u32 {
+        (self.elapsed / self.day_length) as u32 + 1
+    }
+
+    pub fn hour(&self) -> f32 {
+        (self.elapsed / self.day_length).fract() * HOURS_PER_DAY
+    }
+
+    pub fn daylight(&self) -> f32 {
+        let noon_distance = (self.hour() - HOURS_PER_DAY / 2.0).abs() / (HOURS_PER_DAY / 2.0);
+        (1.0 - noon_distance * noon_distance).clamp(0.0, 1.0)
+    }
+
+    pub fn clock_label(&self) -> String {
+        let hour = self.hour();
+        format!(
+            "Day {} {:02}:{:02}",
+            self.day(),
+            hour as u32,
+            (hour.fract() * 60.0) as u32
+        )
+    }
+}
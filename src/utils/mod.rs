@@ -4,7 +4,9 @@ use ggez::glam::Vec2;
 
 pub mod colors;
 pub mod config;
+pub mod day_cycle;
 pub mod grid;
+pub mod theme;
 
 pub fn lerp_angle(a: f32, b: f32, t: f32, choosing_larger_angle: bool) -> f32 {
     let mut a = a.normalize_angle();
@@ -28,6 +30,12 @@ pub fn angle_between(a: f32, b: f32) -> f32 {
     (b - a).abs()
 }
 
+pub fn segments_cross(a: (Vec2, Vec2), b: (Vec2, Vec2)) -> bool {
+    let side = |p: Vec2, q: Vec2, r: Vec2| (q - p).perp_dot(r - p);
+    side(a.0, a.1, b.0) * side(a.0, a.1, b.1) < 0.0
+        && side(b.0, b.1, a.0) * side(b.0, b.1, a.1) < 0.0
+}
+
 pub trait AngleNormalizer {
     fn normalize_angle(self) -> f32;
 }
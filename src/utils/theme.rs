@@ -0,0 +1,141 @@
+use std::{collections::HashMap, fs};
+
+use ggez::{GameError, GameResult, graphics::Color};
+
+use super::config::config_dir;
+
+pub const THEME_DIR: &str = "themes";
+
+#[derive(strum_macros::EnumIter, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Theme {
+    Light,
+    Dark,
+}
+
+impl Theme {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Theme::Light => "light",
+            Theme::Dark => "dark",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            Theme::Light => Theme::Dark,
+            Theme::Dark => Theme::Light,
+        }
+    }
+
+    fn builtin(&self) -> &'static str {
+        match self {
+            Theme::Light => include_str!("../../themes/light.theme"),
+            Theme::Dark => include_str!("../../themes/dark.theme"),
+        }
+    }
+
+    // A `<name>.theme` file in the user's config directory replaces the shipped one.
+    pub fn load(&self) -> ThemeColors {
+        let builtin = || ThemeColors::parse(self.builtin()).expect("Invalid built-in theme.");
+        let Some(path) =
+            config_dir().map(|dir| dir.join(THEME_DIR).join(format!("{}.theme", self.name())))
+        else {
+            return builtin();
+        };
+        let Ok(source) = fs::read_to_string(&path) else {
+            return builtin();
+        };
+        ThemeColors::parse(&source).unwrap_or_else(|e| {
+            eprintln!("Ignoring theme {}: {e}", path.display());
+            builtin()
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ThemeColors {
+    pub background_day: Color,
+    pub background_night: Color,
+    pub panel: Color,
+    pub text: Color,
+    pub station_fill: Color,
+    pub station_outline: Color,
+    pub passenger: Color,
+    pub vehicle_tint: Color,
+    pub cargo_fill: Color,
+    pub cargo_outline: Color,
+    pub depot_fill: Color,
+    pub depot_outline: Color,
+    pub lines: Vec<Color>,
+}
+
+impl ThemeColors {
+    // One `key = value` entry per line, lines starting with `#` are comments. Every key is required.
+    pub fn parse(source: &str) -> GameResult<Self> {
+        let mut entries = HashMap::new();
+        for (number, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                return Err(GameError::ConfigError(format!(
+                    "invalid theme entry on line {}: {}",
+                    number + 1,
+                    line
+                )));
+            };
+            entries.insert(key.trim(), value.trim());
+        }
+        let value = |key: &str| {
+            entries
+                .get(key)
+                .copied()
+                .ok_or_else(|| GameError::ConfigError(format!("missing theme colour `{key}`")))
+        };
+        let color = |key: &str| {
+            value(key).and_then(|value| {
+                parse_color(value).ok_or_else(|| {
+                    GameError::ConfigError(format!("invalid colour for `{key}`: {value}"))
+                })
+            })
+        };
+        let lines = value("lines")?
+            .split(',')
+            .map(|color| parse_color(color.trim()))
+            .collect::<Option<Vec<_>>>()
+            .filter(|lines| !lines.is_empty())
+            .ok_or_else(|| GameError::ConfigError(String::from("invalid line palette")))?;
+        Ok(ThemeColors {
+            background_day: color("background_day")?,
+            background_night: color("background_night")?,
+            panel: color("panel")?,
+            text: color("text")?,
+            station_fill: color("station_fill")?,
+            station_outline: color("station_outline")?,
+            passenger: color("passenger")?,
+            vehicle_tint: color("vehicle_tint")?,
+            cargo_fill: color("cargo_fill")?,
+            cargo_outline: color("cargo_outline")?,
+            depot_fill: color("depot_fill")?,
+            depot_outline: color("depot_outline")?,
+            lines,
+        })
+    }
+}
+
+// `#rrggbb` or `#rrggbbaa`.
+fn parse_color(value: &str) -> Option<Color> {
+    let hex = value.strip_prefix('#')?;
+    if !matches!(hex.len(), 6 | 8) {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+    let alpha = if hex.len() == 8 { channel(6)? } else { 255 };
+    Some(Color::from_rgba(
+        channel(0)?,
+        channel(2)?,
+        channel(4)?,
+        alpha,
+    ))
+}
@@ -0,0 +1,37 @@
+pub const BLOCKING_DISTANCE: f32 = 60.0;
+pub const MAINTENANCE_INTERVAL: f32 = 20000.0;
+pub const MAINTENANCE_TIME: f32 = 5.0;
+
+pub struct Breakdowns {
+    enabled: bool,
+    base_rate: f32,
+    wear_rate: f32,
+    duration: f32,
+}
+
+impl Breakdowns {
+    pub fn new() -> Self {
+        Breakdowns {
+            enabled: false,
+            base_rate: 0.001,
+            wear_rate: 5e-7,
+            duration: 6.0,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.duration
+    }
+
+    pub fn probability(&self, odometer: f32, delta: f32) -> f32 {
+        ((self.base_rate + self.wear_rate * odometer) * delta).min(1.0)
+    }
+}
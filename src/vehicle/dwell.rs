@@ -0,0 +1,69 @@
+use crate::station::types::StationType;
+
+pub const ALIGHTING_TIME: f32 = 0.3;
+pub const BOARDING_TIME: f32 = 0.4;
+pub const DOOR_CLOSING_TIME: f32 = 0.5;
+pub const DOORS_PER_CARRIAGE: usize = 2;
+// A long frame may let at most this many riders through at once, so nobody teleports aboard.
+pub const MAX_FLOW_BACKLOG: f32 = 8.0;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum DwellPhase {
+    Alighting,
+    Boarding,
+    Closing(f32),
+}
+
+pub struct Dwell {
+    phase: DwellPhase,
+    flow_time: f32,
+}
+
+impl Dwell {
+    pub fn new() -> Self {
+        Dwell {
+            phase: DwellPhase::Alighting,
+            flow_time: 0.0,
+        }
+    }
+
+    pub fn phase(&self) -> DwellPhase {
+        self.phase
+    }
+
+    pub fn set_phase(&mut self, phase: DwellPhase) {
+        self.phase = phase;
+        self.flow_time = 0.0;
+    }
+
+    pub fn open_doors(&mut self) {
+        self.set_phase(DwellPhase::Alighting);
+    }
+
+    pub fn flow_interval(
+        base_time: f32,
+        doors: usize,
+        station_type: StationType,
+        load_factor: f32,
+        station_crowding: f32,
+    ) -> f32 {
+        base_time * station_type.boarding_time_factor() * (1.0 + load_factor + station_crowding)
+            / doors.max(1) as f32
+    }
+
+    pub fn advance(&mut self, delta: f32, interval: f32) {
+        self.flow_time = (self.flow_time + delta).min(interval * MAX_FLOW_BACKLOG);
+    }
+
+    pub fn try_flow(&mut self, interval: f32) -> bool {
+        if self.flow_time < interval {
+            return false;
+        }
+        self.flow_time -= interval;
+        true
+    }
+
+    pub fn hold_flow(&mut self, interval: f32) {
+        self.flow_time = self.flow_time.min(interval);
+    }
+}
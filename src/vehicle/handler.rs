@@ -2,15 +2,30 @@ use std::collections::HashMap;
 
 use ggez::{graphics::{Canvas, Color}, Context};
 
-use crate::{route::handler::RouteHandler, shape::{palette::ShapePalette, ShapeBuilder}, station::handler::StationHandler};
+use rand::rngs::StdRng;
 
-use super::Vehicle;
+use crate::{
+    depot::handler::DepotHandler,
+    passenger::routing::RoutingTable,
+    route::{Route, handler::RouteHandler},
+    shape::{ShapeBuilder, palette::ShapePalette},
+    station::handler::StationHandler,
+};
+
+use super::{
+    Vehicle,
+    breakdown::{BLOCKING_DISTANCE, Breakdowns},
+};
+
+pub const MAX_VEHICLES: u32 = 7;
 
 pub struct VehicleHandler {
     metros: Vec<Box<dyn Vehicle>>,
     route_map: HashMap<usize, Vec<usize>>,
+    route_lengths: HashMap<usize, (usize, f32)>,
     max_count: u32,
     shapes: ShapeBuilder,
+    breakdowns: Breakdowns,
 }
 
 impl VehicleHandler {
@@ -18,8 +33,10 @@ impl VehicleHandler {
         VehicleHandler {
             metros: vec![],
             route_map: HashMap::new(),
+            route_lengths: HashMap::new(),
             max_count,
-            shapes
+            shapes,
+            breakdowns: Breakdowns::new(),
         }
     }
 
@@ -38,9 +55,13 @@ impl VehicleHandler {
         return vec![];
     }
 
-    pub fn add_vehicle(&mut self, mut vehicle: Box<dyn Vehicle>) {
+    pub fn get_mut(&mut self, index: usize) -> &mut Box<dyn Vehicle> {
+        self.metros.get_mut(index).unwrap()
+    }
+
+    pub fn add_vehicle(&mut self, mut vehicle: Box<dyn Vehicle>) -> bool {
         if self.max_count == self.metros.len() as u32 {
-            return;
+            return false;
         }
         let route = vehicle.route();
         vehicle.set_id(self.metros.len());
@@ -53,15 +74,127 @@ impl VehicleHandler {
             .get_mut(&route)
             .unwrap()
             .push(self.metros.len() - 1);
+        true
+    }
+
+    pub fn set_max_count(&mut self, max_count: u32) {
+        self.max_count = max_count;
+    }
+
+    // Segment indices change with the route geometry, so trains on a rebuilt route are carried
+    // over onto the new path.
+    pub fn remap(&mut self, route: &Route, stations: &StationHandler) {
+        for &id in self.route_map.get(&route.id()).into_iter().flatten() {
+            self.metros[id].remap(route, stations);
+        }
+    }
+
+    // Station ids shift when a station is placed or removed.
+    pub fn renumber_stations(&mut self, renumber: impl Fn(usize) -> usize) {
+        for metro in self.metros.iter_mut() {
+            metro.renumber_stations(&renumber);
+        }
+    }
+
+    pub fn remove_last_vehicle(&mut self) -> Option<Box<dyn Vehicle>> {
+        let vehicle = self.metros.pop()?;
+        if let Some(ids) = self.route_map.get_mut(&vehicle.route()) {
+            ids.retain(|&id| id != vehicle.id());
+        }
+        Some(vehicle)
+    }
+
+    pub fn set_route(&mut self, vehicle: usize, route: usize) {
+        let previous = self.metros[vehicle].route();
+        if let Some(ids) = self.route_map.get_mut(&previous) {
+            ids.retain(|&id| id != vehicle);
+        }
+        self.route_map.entry(route).or_default().push(vehicle);
+        self.metros[vehicle].set_route(route);
+    }
+
+    pub fn breakdowns_mut(&mut self) -> &mut Breakdowns {
+        &mut self.breakdowns
+    }
+
+    pub fn update(
+        &mut self,
+        delta: f32,
+        routes: &mut RouteHandler,
+        stations: &mut StationHandler,
+        routing: &RoutingTable,
+        depots: &DepotHandler,
+        rng: &mut StdRng,
+    ) {
+        for route in routes.iter() {
+            let length = (route.length(), route.total_length());
+            if self
+                .route_lengths
+                .insert(route.id(), length)
+                .is_some_and(|previous| previous != length)
+            {
+                self.remap(route, stations);
+            }
+        }
+        for vehicle in self.metros.iter_mut() {
+            // A rebuilt route can have fewer segments than the one the train was running on.
+            let segments = routes.get(vehicle.route()).length();
+            if segments > 0 && vehicle.segment() >= segments {
+                vehicle.set_segment(segments - 1);
+                vehicle.set_distance(0.0);
+            }
+            vehicle.roll_breakdown(rng, &self.breakdowns, delta);
+            vehicle.update(routes, stations, routing, depots, delta);
+        }
+        self.update_blocking(routes);
     }
 
-    pub fn update(&mut self, delta: f32, routes: &RouteHandler, stations: &mut StationHandler) {
+    fn update_blocking(&mut self, routes: &RouteHandler) {
         for vehicle in self.metros.iter_mut() {
-            vehicle.update(routes, stations, delta);
+            vehicle.set_blocked(false);
+        }
+        loop {
+            let obstacles: Vec<(usize, f32, f32)> = self
+                .metros
+                .iter()
+                .filter(|v| v.is_broken_down() || v.is_blocked())
+                .map(|v| (v.route(), v.direction(), v.route_position(routes)))
+                .collect();
+            let mut changed = false;
+            for vehicle in self.metros.iter_mut() {
+                if vehicle.is_broken_down() || vehicle.is_blocked() || vehicle.is_stopping() {
+                    continue;
+                }
+                let route = routes.get(vehicle.route());
+                let position = vehicle.route_position(routes);
+                let blocked = obstacles
+                    .iter()
+                    .any(|&(other_route, direction, other_position)| {
+                        let mut gap = (other_position - position) * direction;
+                        if route.is_looped() {
+                            gap = gap.rem_euclid(route.total_length());
+                        }
+                        other_route == vehicle.route()
+                            && direction == vehicle.direction()
+                            && gap > 0.0
+                            && gap < BLOCKING_DISTANCE
+                    });
+                if blocked {
+                    vehicle.set_blocked(true);
+                    changed = true;
+                }
+            }
+            if !changed {
+                break;
+            }
         }
     }
     
     pub fn shapes(&self) -> &ShapeBuilder {
         &self.shapes
     }
+
+    pub fn set_palette(&mut self, ctx: &Context, palette: ShapePalette) {
+        self.shapes.set_palette(ctx, palette);
+    }
 }
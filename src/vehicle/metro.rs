@@ -5,14 +5,27 @@ use ggez::{
 };
 
 use crate::{
-    passenger::{Passenger, PassengerState},
-    route::{handler::RouteHandler, segment::VehicleState},
+    analytics::Counters,
+    depot::handler::DepotHandler,
+    passenger::{
+        Passenger, PassengerState,
+        routing::{NextHop, RoutingTable},
+    },
+    route::{Route, handler::RouteHandler, segment::VehicleState},
     shape::ShapeBuilder,
     station::{handler::StationHandler, types::StationShape},
-    utils::{AngleNormalizer, lerp_angle},
+    utils::{AngleCalc, AngleNormalizer, lerp_angle},
 };
 
-use super::Vehicle;
+use super::{
+    Vehicle,
+    breakdown::{MAINTENANCE_INTERVAL, MAINTENANCE_TIME},
+    dwell::{
+        ALIGHTING_TIME, BOARDING_TIME, DOOR_CLOSING_TIME, DOORS_PER_CARRIAGE, Dwell, DwellPhase,
+    },
+};
+
+pub const CARRIAGE_CAPACITY: usize = 6;
 
 pub struct Metro {
     id: usize,
@@ -26,7 +39,16 @@ pub struct Metro {
     speed: f32,
     max_speed: f32,
     waiting_time: f32,
-    max_waiting_time: f32,
+    min_dwell_time: f32,
+    dwell: Dwell,
+    trip: Option<(f32, usize)>,
+    carriages: usize,
+    relocation: Option<usize>,
+
+    odometer: f32,
+    breakdown_time: f32,
+    blocked: bool,
+    maintenance: Option<(Vec2, Vec2, f32)>,
 
     direction: f32,
 
@@ -36,6 +58,7 @@ pub struct Metro {
     mesh: Mesh,
 
     passengers: Vec<Passenger>,
+    counters: Counters,
 }
 
 impl Metro {
@@ -54,8 +77,17 @@ impl Metro {
             rotation: 0.0,
             next_rotation: 0.0,
             passengers: vec![],
+            counters: Counters::default(),
             waiting_time: 0.0,
-            max_waiting_time: 1.0,
+            min_dwell_time: 1.0,
+            dwell: Dwell::new(),
+            trip: None,
+            carriages: 1,
+            relocation: None,
+            odometer: 0.0,
+            breakdown_time: 0.0,
+            blocked: false,
+            maintenance: None,
             mesh: Mesh::from_data(
                 ctx,
                 MeshBuilder::new()
@@ -72,29 +104,253 @@ impl Metro {
         }
     }
 
+    fn hold_at_platform(&mut self, routes: &mut RouteHandler, station: usize) {
+        self.stopping = true;
+        self.waiting_time = self.min_dwell_time;
+        self.dwell.open_doors();
+
+        let route = routes.get_mut(self.route);
+        if !route.timetable().is_enabled() {
+            self.trip = None;
+            return;
+        }
+        if station == route.stops()[0].index() {
+            self.trip = route.timetable_mut().dispatch().map(|start| (start, 0));
+        } else if let Some((start, call)) = self.trip {
+            self.trip = Some((start, call + 1)).filter(|&(_, call)| {
+                route
+                    .calls()
+                    .get(call)
+                    .is_some_and(|&(call_station, _)| call_station == station)
+            });
+        }
+
+        if let Some((start, call)) = self.trip {
+            let clock = route.timetable().clock();
+            let departure = route.scheduled_departure(start, call).unwrap_or(clock);
+            if call > 0 {
+                let scheduled_arrival = departure - route.timetable().dwell_time();
+                route
+                    .timetable_mut()
+                    .record_arrival(call, clock - scheduled_arrival);
+            }
+            self.waiting_time = departure - clock;
+        }
+    }
+
+    // Seats the train at the platform of a station once the route geometry has been rebuilt.
+    fn resolve_relocation(&mut self, routes: &RouteHandler) {
+        let Some(station) = self.relocation.take() else {
+            return;
+        };
+        let (segment, distance) = routes
+            .get(self.route)
+            .platform_segment(station)
+            .unwrap_or((0, 0.0));
+        self.segment = segment;
+        self.distance = distance;
+        self.direction = 1.0;
+        self.speed = 0.0;
+        self.trip = None;
+        self.maintenance = None;
+        self.stopping = true;
+        self.waiting_time = self.min_dwell_time;
+        self.dwell.open_doors();
+        self.position = routes.get(self.route).calculate_position(segment, distance);
+    }
+
+    fn needs_maintenance(&self) -> bool {
+        self.odometer > MAINTENANCE_INTERVAL
+    }
+
+    // The stop on this line closest to a depot, so a worn train stays in service until it
+    // reaches that platform instead of leaving the line wherever it happens to be.
+    fn depot_platform(
+        &self,
+        routes: &RouteHandler,
+        stations: &StationHandler,
+        depots: &DepotHandler,
+    ) -> Option<(usize, Vec2)> {
+        routes
+            .get(self.route)
+            .stops()
+            .iter()
+            .filter_map(|stop| {
+                let position = stations.get(stop.index()).position();
+                let depot = depots.nearest_serving(position)?.position();
+                Some((stop.index(), position.distance(depot), depot))
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(station, _, depot)| (station, depot))
+    }
+
+    // Only the short hop between the depot platform and the depot itself is off the line.
+    fn update_maintenance(&mut self, delta: f32) {
+        let Some((platform, depot, remaining)) = self.maintenance else {
+            return;
+        };
+        let target = if remaining > 0.0 { depot } else { platform };
+        let offset = target - self.position;
+        if offset.length() > 1.0 {
+            self.set_rotation(offset.angle());
+        }
+        self.position += offset.clamp_length_max(self.max_speed * 0.5 * delta);
+        self.rotation = lerp_angle(self.rotation, self.next_rotation, 25.0 * delta, false);
+        if self.position.distance(target) > 1.0 {
+            return;
+        }
+        if remaining > 0.0 {
+            self.maintenance = Some((platform, depot, remaining - delta));
+        } else {
+            self.maintenance = None;
+            self.odometer = 0.0;
+            self.waiting_time = self.min_dwell_time;
+            self.dwell.open_doors();
+        }
+    }
+
+    fn dwell_at_platform(
+        &mut self,
+        stations: &mut StationHandler,
+        routing: &RoutingTable,
+        station_id: usize,
+        served: &[StationShape],
+        boarding: bool,
+        delta: f32,
+    ) -> bool {
+        self.waiting_time -= delta;
+        let station = stations.get_mut(station_id);
+        let doors = self.carriages * DOORS_PER_CARRIAGE;
+        let load_factor = self.passengers.len() as f32 / self.capacity() as f32;
+
+        match self.dwell.phase() {
+            DwellPhase::Alighting => {
+                let interval = Dwell::flow_interval(
+                    ALIGHTING_TIME,
+                    doors,
+                    station.station_type(),
+                    load_factor,
+                    0.0,
+                );
+                self.dwell.advance(delta, interval);
+                // Riders whose quickest way on is a walking link change here as well.
+                let walks = |p: &Passenger| {
+                    matches!(
+                        routing.next_hop(station_id, p.kind()),
+                        Some(NextHop::Walk(_))
+                    )
+                };
+                while let Some(i) = self
+                    .passengers
+                    .iter()
+                    .position(|p| p.kind() == station.kind() || p.is_out_of_patience() || walks(p))
+                {
+                    if !self.dwell.try_flow(interval) {
+                        return false;
+                    }
+                    let passenger = self.passengers.remove(i);
+                    self.counters
+                        .record_alighting(&passenger, passenger.kind() == station.kind());
+                    if passenger.kind() != station.kind()
+                        && !passenger.is_out_of_patience()
+                        && walks(&passenger)
+                    {
+                        station.transfer_passenger(passenger, self);
+                    } else {
+                        station.receive_passenger(passenger, self);
+                    }
+                }
+                self.dwell.set_phase(DwellPhase::Boarding);
+            }
+            DwellPhase::Boarding => {
+                let interval = Dwell::flow_interval(
+                    BOARDING_TIME,
+                    doors,
+                    station.station_type(),
+                    load_factor,
+                    station.crowding(),
+                );
+                self.dwell.advance(delta, interval);
+                while self.dwell.try_flow(interval) {
+                    let taken = if boarding {
+                        station.try_take_vehicle(self, served)
+                    } else {
+                        None
+                    };
+                    match taken {
+                        Some(mut passenger) => {
+                            self.counters.record_boarding(&passenger);
+                            passenger.set_state(PassengerState::OnVehicle);
+                            self.passengers.push(passenger);
+                        }
+                        None => {
+                            self.dwell.hold_flow(interval);
+                            if self.waiting_time <= 0.0 {
+                                self.dwell.set_phase(DwellPhase::Closing(DOOR_CLOSING_TIME));
+                            }
+                            break;
+                        }
+                    }
+                }
+            }
+            DwellPhase::Closing(remaining) => {
+                if remaining <= delta {
+                    return true;
+                }
+                self.dwell.set_phase(DwellPhase::Closing(remaining - delta));
+            }
+        }
+        false
+    }
+
     fn try_update_vehicle_if_not_stopping(
         &mut self,
-        routes: &RouteHandler,
+        routes: &mut RouteHandler,
         stations: &mut StationHandler,
+        routing: &RoutingTable,
+        depots: &DepotHandler,
         delta: f32,
     ) -> bool {
-        let segment = routes.get(self.route).get(self.segment);
+        let segment = *routes.get(self.route).get(self.segment);
+
+        let route = routes.get(self.route);
+        if self.stopping
+            && self.trip.is_none()
+            && route.timetable().is_enabled()
+            && !route.timetable().departures().is_empty()
+            && segment.station() == route.stops()[0].index()
+        {
+            self.hold_at_platform(routes, segment.station());
+        }
 
         if self.stopping {
-            let passengers = stations.get_mut(segment.station()).try_take_vehicle(self);
-            if passengers.is_empty() {
-                self.waiting_time -= delta;
-            } else {
-                self.waiting_time += passengers.len() as f32 * 0.2;
-                for passenger in passengers {
-                    let mut p = passenger.clone();
-                    p.set_state(PassengerState::OnVehicle);
-                    self.passengers.push(p);
+            self.stop_station = segment.station();
+            let depot = self
+                .needs_maintenance()
+                .then(|| self.depot_platform(routes, stations, depots))
+                .flatten()
+                .filter(|&(station, _)| station == segment.station())
+                .map(|(_, depot)| depot);
+            let route = routes.get(self.route);
+            let served = route.served_shapes(stations, segment.station(), self.direction);
+            if self.dwell_at_platform(
+                stations,
+                routing,
+                segment.station(),
+                &served,
+                depot.is_none(),
+                delta,
+            ) {
+                if let Some(depot) = depot.filter(|_| self.passengers.is_empty()) {
+                    self.maintenance = Some((self.position, depot, MAINTENANCE_TIME));
+                    return true;
                 }
-            }
-            if self.waiting_time <= 0.0 {
                 self.stopping = false;
                 if !self.try_reverse_direction_at_end(routes) {
+                    // Leaving the platform skips the boundary crossing that move_vehicle records.
+                    routes
+                        .get_mut(self.route)
+                        .record_flow(self.segment, self.passengers.len());
                     self.start_next_segment(routes);
                     self.distance = segment.progress(0.0, self.direction) * segment.length(); // Move to start of the LeavePlatform = platform
                     self.speed = 0.0;
@@ -106,7 +362,7 @@ impl Metro {
 
         if !self.stopping {
             match segment.state() {
-                VehicleState::Moving => {
+                VehicleState::Moving | VehicleState::Curve(_) => {
                     self.speed += (self.max_speed - self.speed) * 0.25 * delta;
                 }
                 VehicleState::LastPlatform(arrive_direction) => {
@@ -116,8 +372,7 @@ impl Metro {
                                 + segment.distance_to_end(self.distance, self.direction) / 50.0)
                                 .min(1.0);
                         if segment.end(self.distance, self.direction) {
-                            self.stopping = true;
-                            self.waiting_time = self.max_waiting_time;
+                            self.hold_at_platform(routes, segment.station());
                         }
                     } else {
                         self.speed = self.max_speed
@@ -130,8 +385,7 @@ impl Metro {
                         self.speed = self.max_speed
                             * (1.05 - segment.progress(self.distance, self.direction));
                         if segment.end(self.distance, self.direction) {
-                            self.stopping = true;
-                            self.waiting_time = self.max_waiting_time;
+                            self.hold_at_platform(routes, segment.station());
                         }
                     } else {
                         self.speed =
@@ -143,13 +397,7 @@ impl Metro {
                         self.speed = self.max_speed
                             * (1.05 - segment.progress(self.distance, self.direction));
                         if segment.end(self.distance, self.direction) {
-                            self.stopping = true;
-                            self.waiting_time = self.max_waiting_time
-                                + stations
-                                    .get_mut(segment.station())
-                                    .try_take_vehicle(self)
-                                    .len() as f32
-                                    * 0.25;
+                            self.hold_at_platform(routes, segment.station());
                         }
                     } else {
                         self.speed =
@@ -171,12 +419,12 @@ impl Vehicle for Metro {
         self.id = id
     }
 
+    fn capacity(&self) -> usize {
+        self.carriages * CARRIAGE_CAPACITY
+    }
+
     fn available_spaces(&self) -> usize {
-        if self.passengers.len() > 6 {
-            0
-        } else {
-            6 - self.passengers.len()
-        }
+        self.capacity().saturating_sub(self.passengers.len())
     }
 
     fn draw(&self, canvas: &mut Canvas, shapes: &ShapeBuilder, color: Color) {
@@ -185,16 +433,50 @@ impl Vehicle for Metro {
             DrawParam::default()
                 .dest(self.position)
                 .rotation(self.rotation)
-                .scale([20.0, 10.0])
-                .color(color),
+                .scale([20.0 * self.carriages as f32, 10.0])
+                .color(if self.breakdown_time > 0.0 {
+                    Color::new(color.r, color.g, color.b, 0.4)
+                } else {
+                    color
+                }),
         );
     }
 
-    fn update(&mut self, routes: &RouteHandler, stations: &mut StationHandler, delta: f32) {
-        if self.try_update_vehicle_if_not_stopping(routes, stations, delta) {
+    fn update(
+        &mut self,
+        routes: &mut RouteHandler,
+        stations: &mut StationHandler,
+        routing: &RoutingTable,
+        depots: &DepotHandler,
+        delta: f32,
+    ) {
+        for passenger in self.passengers.iter_mut() {
+            passenger.ride(delta);
+        }
+        self.resolve_relocation(routes);
+        if self.maintenance.is_some() {
+            self.update_maintenance(delta);
+            return;
+        }
+        if self.breakdown_time > 0.0 {
+            self.breakdown_time -= delta;
+            self.speed = 0.0;
+            return;
+        }
+        if self.try_update_vehicle_if_not_stopping(routes, stations, routing, depots, delta) {
             return;
         }
+        if self.blocked {
+            self.speed = 0.0;
+            return;
+        }
+        let segment = self.segment;
         self.move_vehicle(routes, stations, delta);
+        if self.segment != segment {
+            routes
+                .get_mut(self.route)
+                .record_flow(segment, self.passengers.len());
+        }
         self.rotation = lerp_angle(self.rotation, self.next_rotation, 25.0 * delta, false);
     }
 
@@ -202,6 +484,22 @@ impl Vehicle for Metro {
         &self.passengers
     }
 
+    fn take_passengers(&mut self) -> Vec<Passenger> {
+        std::mem::take(&mut self.passengers)
+    }
+
+    fn counters(&self) -> &Counters {
+        &self.counters
+    }
+
+    fn carriages(&self) -> usize {
+        self.carriages
+    }
+
+    fn set_carriages(&mut self, carriages: usize) {
+        self.carriages = carriages;
+    }
+
     fn position(&self) -> Vec2 {
         self.position
     }
@@ -218,6 +516,34 @@ impl Vehicle for Metro {
         self.speed
     }
 
+    fn is_stopping(&self) -> bool {
+        self.stopping
+    }
+
+    fn odometer(&self) -> f32 {
+        self.odometer
+    }
+
+    fn add_odometer(&mut self, distance: f32) {
+        self.odometer += distance;
+    }
+
+    fn is_broken_down(&self) -> bool {
+        self.breakdown_time > 0.0
+    }
+
+    fn break_down(&mut self, duration: f32) {
+        self.breakdown_time = duration;
+    }
+
+    fn is_blocked(&self) -> bool {
+        self.blocked
+    }
+
+    fn set_blocked(&mut self, blocked: bool) {
+        self.blocked = blocked;
+    }
+
     fn direction(&self) -> f32 {
         self.direction
     }
@@ -230,6 +556,46 @@ impl Vehicle for Metro {
         self.route
     }
 
+    fn set_route(&mut self, route: usize) {
+        self.route = route;
+    }
+
+    fn relocate(&mut self, station: usize) {
+        self.relocation = Some(station);
+    }
+
+    fn renumber_stations(&mut self, renumber: &dyn Fn(usize) -> usize) {
+        self.stop_station = renumber(self.stop_station);
+        self.relocation = self.relocation.map(renumber);
+    }
+
+    // Carries the train over to a rebuilt path: one at a platform keeps its station, a moving
+    // one continues from the nearest point. Only a train whose station left the line is
+    // re-seated at the nearest stop.
+    fn remap(&mut self, route: &Route, stations: &StationHandler) {
+        if self.relocation.is_some() {
+            return;
+        }
+        let seat = if self.stopping {
+            route.platform_stop(self.stop_station, self.direction)
+        } else {
+            route.project(self.position)
+        };
+        let Some((segment, distance)) = seat else {
+            if let Some(station) = route.nearest_stop(stations, self.position) {
+                self.relocate(station);
+            }
+            return;
+        };
+        self.segment = segment;
+        self.distance = distance;
+        let position = route.calculate_position(segment, distance);
+        match self.maintenance {
+            Some((_, depot, remaining)) => self.maintenance = Some((position, depot, remaining)),
+            None => self.position = position,
+        }
+    }
+
     fn segment(&self) -> usize {
         self.segment
     }
@@ -1,20 +1,27 @@
 use std::f32::consts::PI;
 
+use breakdown::Breakdowns;
 use ggez::{glam::Vec2, graphics::{Canvas, Color}, Context};
+use rand::{Rng, rngs::StdRng};
 
 use crate::{
-    passenger::Passenger,
+    analytics::Counters,
+    depot::handler::DepotHandler,
+    passenger::{Passenger, routing::RoutingTable},
     route::{handler::RouteHandler, segment::{Segment, VehicleState}, Route},
     shape::ShapeBuilder,
     station::{handler::StationHandler, types::StationShape},
 };
 
+pub mod breakdown;
+pub mod dwell;
 pub mod handler;
 pub mod metro;
 
 pub trait Vehicle {
     fn id(&self) -> usize;
     fn set_id(&mut self, id: usize);
+    fn capacity(&self) -> usize;
     fn available_spaces(&self) -> usize;
 
     fn position(&self) -> Vec2;
@@ -22,16 +29,40 @@ pub trait Vehicle {
     fn set_rotation(&mut self, rotation: f32);
 
     fn speed(&self) -> f32;
+    fn is_stopping(&self) -> bool;
+
+    fn odometer(&self) -> f32;
+    fn add_odometer(&mut self, distance: f32);
+    fn is_broken_down(&self) -> bool;
+    fn break_down(&mut self, duration: f32);
+    fn is_blocked(&self) -> bool;
+    fn set_blocked(&mut self, blocked: bool);
 
     fn direction(&self) -> f32;
     fn reverse_direction(&mut self);
 
-    fn update(&mut self, routes: &RouteHandler, stations: &mut StationHandler, delta: f32);
+    fn update(
+        &mut self,
+        routes: &mut RouteHandler,
+        stations: &mut StationHandler,
+        routing: &RoutingTable,
+        depots: &DepotHandler,
+        delta: f32,
+    );
     fn draw(&self, canvas: &mut Canvas, shapes: &ShapeBuilder, color: Color);
 
     fn passengers(&self) -> &Vec<Passenger>;
+    fn take_passengers(&mut self) -> Vec<Passenger>;
+    fn counters(&self) -> &Counters;
+
+    fn carriages(&self) -> usize;
+    fn set_carriages(&mut self, carriages: usize);
 
     fn route(&self) -> usize;
+    fn set_route(&mut self, route: usize);
+    fn relocate(&mut self, station: usize);
+    fn remap(&mut self, route: &Route, stations: &StationHandler);
+    fn renumber_stations(&mut self, renumber: &dyn Fn(usize) -> usize);
 
     fn segment(&self) -> usize;
     fn set_segment(&mut self, segment: usize);
@@ -39,6 +70,28 @@ pub trait Vehicle {
     fn distance(&self) -> f32;
     fn set_distance(&mut self, distance: f32);
 
+    fn route_position(&self, routes: &RouteHandler) -> f32 {
+        routes
+            .get(self.route())
+            .path_distance(self.segment(), self.distance())
+    }
+
+    fn next_stop(&self, routes: &RouteHandler) -> Option<usize> {
+        routes
+            .get(self.route())
+            .next_call(self.route_position(routes), self.direction())
+    }
+
+    fn roll_breakdown(&mut self, rng: &mut StdRng, breakdowns: &Breakdowns, delta: f32) {
+        if breakdowns.is_enabled()
+            && !self.is_stopping()
+            && !self.is_broken_down()
+            && rng.random::<f32>() < breakdowns.probability(self.odometer(), delta)
+        {
+            self.break_down(breakdowns.duration());
+        }
+    }
+
     fn get_segment<'a>(&self, routes: &'a RouteHandler) -> &'a Segment {
         routes.get(self.route()).get(self.segment())
     }
@@ -88,6 +141,7 @@ pub trait Vehicle {
                 + if self.direction() == -1.0 { PI } else { 0.0 },
         );
         self.set_distance(self.distance() + delta * self.speed() * self.direction());
+        self.add_odometer(delta * self.speed());
     }
 }
 /*
@@ -1,25 +1,73 @@
+use std::{mem, time::Instant};
+
 use ggez::{
     Context, GameError, GameResult,
     event::EventHandler,
     glam::Vec2,
     graphics::{Canvas, Color, DrawParam, FilterMode, Quad, Rect},
+    input::{
+        keyboard::{KeyCode, KeyInput, KeyMods},
+        mouse::MouseButton,
+    },
 };
 use rand::{SeedableRng, rngs::StdRng};
 
 use crate::{
+    analytics::{
+        export::{DEFAULT_EXPORT_DIR, export},
+        handler::Analytics,
+    },
+    depot::handler::DepotHandler,
+    edit::{
+        Command, EditError, Network, Resources, STARTING_CARRIAGES, STARTING_LINES,
+        STARTING_TRAINS, handler::EditHistory, mode::GameMode,
+    },
+    passenger::{
+        routing::RoutingTable,
+        satisfaction::{HARD_MODE_MIN_SATISFACTION, Satisfaction},
+    },
     route::{
         handler::RouteHandler,
         stop::{Stop, StopSide},
     },
+    scene::{Scene, SceneEvent, Transition, handler::SceneHandler},
     shape::{ShapeBuilder, palette::ShapePalette},
-    station::{handler::StationHandler, types::StationShape},
-    utils::colors::Colors,
-    vehicle::{handler::VehicleHandler, metro::Metro},
+    station::{
+        handler::StationHandler,
+        types::{StationShape, StationType},
+    },
+    ui::{
+        creative::CreativePanel,
+        debug::DebugOverlay,
+        editor::RouteEditor,
+        heatmap::Heatmap,
+        hud::draw_hud,
+        inspector::{Inspector, Selection},
+        stats::StatsScreen,
+        timetable::TimetableEditor,
+    },
+    utils::{
+        colors::Colors,
+        config::Settings,
+        day_cycle::{DAY_LENGTH, DayCycle},
+    },
+    vehicle::{
+        handler::{MAX_VEHICLES, VehicleHandler},
+        metro::Metro,
+    },
 };
 
 pub struct MetroWorld {
+    seed: u64,
+    settings: Settings,
+    scenes: SceneHandler,
     rng: StdRng,
     time: f32,
+    day_cycle: DayCycle,
+    satisfaction: Satisfaction,
+    hard_mode: bool,
+    game_over: Option<&'static str>,
+    mode: GameMode,
 
     logical_width: f32,
     logical_height: f32,
@@ -27,70 +75,262 @@ pub struct MetroWorld {
 
     stations: StationHandler,
     routes: RouteHandler,
+    routing: RoutingTable,
     vehicles: VehicleHandler,
+    depots: DepotHandler,
+    analytics: Analytics,
+    export_dir: Option<String>,
+    od_path: Option<String>,
+    resources: Resources,
+    history: EditHistory,
+
+    timetable_editor: TimetableEditor,
+    inspector: Inspector,
+    heatmap: Heatmap,
+    debug_overlay: DebugOverlay,
+    stats_screen: StatsScreen,
+    editor: RouteEditor,
+    creative: CreativePanel,
 }
 
 impl MetroWorld {
-    pub fn new(ctx: &mut Context, seed: u64) -> Self {
+    pub fn new(ctx: &mut Context, seed: u64, settings: Settings) -> Self {
         let (logical_width, logical_height) = ctx.gfx.drawable_size();
         let mut stations = StationHandler::new(
             ShapeBuilder::new(
                 ctx,
-                ShapePalette::new(Color::WHITE, Color::from_rgb(5, 5, 2)),
+                ShapePalette::new(Colors::station_fill(), Colors::station_outline()),
             ),
-            ShapeBuilder::new(ctx, ShapePalette::fill(Color::from_rgb(5, 5, 2))),
+            ShapeBuilder::new(ctx, ShapePalette::fill(Colors::passenger())),
+        );
+        stations.add_station(
+            StationShape::Circle,
+            StationType::Residential,
+            Vec2::new(100.0, 100.0),
+        );
+        stations.add_station(
+            StationShape::Triangle,
+            StationType::Residential,
+            Vec2::new(200.0, 200.0),
+        );
+        stations.add_station(
+            StationShape::Circle,
+            StationType::Normal,
+            Vec2::new(300.0, 100.0),
+        );
+        stations.add_station(
+            StationShape::Square,
+            StationType::Commercial,
+            Vec2::new(400.0, 200.0),
+        );
+        stations.add_station(
+            StationShape::Square,
+            StationType::Commercial,
+            Vec2::new(500.0, 100.0),
         );
-        stations.add_station(StationShape::Circle, Vec2::new(100.0, 100.0));
-        stations.add_station(StationShape::Circle, Vec2::new(200.0, 200.0));
-        stations.add_station(StationShape::Circle, Vec2::new(300.0, 100.0));
-        stations.add_station(StationShape::Circle, Vec2::new(400.0, 200.0));
-        stations.add_station(StationShape::Circle, Vec2::new(500.0, 100.0));
+
+        stations.set_walking_radius(settings.walking_radius);
 
         let mut routes = RouteHandler::new();
+        routes.set_line_style(settings.line_style);
+        routes.set_palette(settings.line_palette.colors());
+        routes.set_marking(settings.line_marking);
+        routes.set_labels_visible(settings.line_labels);
         routes.add_route(
-            vec![
-                Stop::new(0, StopSide::Right),
-                Stop::new(1, StopSide::Left),
-                Stop::new(2, StopSide::Left),
-                Stop::new(3, StopSide::Right),
-            ],
+            vec![Stop::auto(0), Stop::auto(1), Stop::auto(2), Stop::auto(3)],
             false,
         );
         routes.add_route(
             vec![
                 Stop::new(4, StopSide::Right),
-                Stop::new(3, StopSide::Left),
-                Stop::new(2, StopSide::Left),
-                Stop::new(1, StopSide::Right),
+                Stop::auto(3),
+                Stop::auto(2),
+                Stop::auto(1),
             ],
             false,
         );
 
         let mut metros = VehicleHandler::new(
-            7,
+            MAX_VEHICLES,
             ShapeBuilder::new(
                 ctx,
-                ShapePalette::new(
-                    Color::from_rgb(125, 125, 125),
-                    Color::from_rgb(156, 156, 156),
-                ),
+                ShapePalette::new(Colors::cargo_fill(), Colors::cargo_outline()),
             ),
         );
         metros.add_vehicle(Box::new(Metro::new(&ctx, 0)));
         metros.add_vehicle(Box::new(Metro::new(&ctx, 1)));
 
+        let mut depots = DepotHandler::new(ctx);
+        depots.add_depot(Vec2::new(300.0, 260.0));
+
         MetroWorld {
+            seed,
+            settings,
+            scenes: SceneHandler::new(),
             rng: StdRng::seed_from_u64(seed),
             time: 0.0,
+            day_cycle: DayCycle::new(DAY_LENGTH),
+            satisfaction: Satisfaction::new(),
+            hard_mode: false,
+            game_over: None,
+            mode: GameMode::Classic,
             stations,
             routes,
+            routing: RoutingTable::new(),
             vehicles: metros,
+            depots,
+            analytics: Analytics::new(),
+            export_dir: None,
+            od_path: None,
+            resources: Resources::new(STARTING_LINES, STARTING_TRAINS, STARTING_CARRIAGES),
+            history: EditHistory::new(),
+            timetable_editor: TimetableEditor::new(),
+            inspector: Inspector::new(),
+            heatmap: Heatmap::new(),
+            debug_overlay: DebugOverlay::new(),
+            stats_screen: StatsScreen::new(),
+            editor: RouteEditor::new(),
+            creative: CreativePanel::new(),
             logical_width,
             logical_height,
             screen_transform_rect: Rect::new(0.0, 0.0, logical_width, logical_height),
         }
     }
 
+    pub fn load_od_matrix(&mut self, path: &str) -> GameResult {
+        self.stations.load_od_matrix(path)?;
+        self.od_path = Some(path.to_string());
+        Ok(())
+    }
+
+    pub fn set_export_dir(&mut self, dir: &str) {
+        self.export_dir = Some(dir.to_string());
+    }
+
+    fn export_metrics(&self) {
+        let dir = self.export_dir.as_deref().unwrap_or(DEFAULT_EXPORT_DIR);
+        match export(&self.analytics, dir) {
+            Ok(()) => println!("Exported metrics to {dir}"),
+            Err(e) => eprintln!("Failed to export metrics to {dir}: {e}"),
+        }
+    }
+
+    fn edit(
+        &mut self,
+        ctx: &Context,
+        action: impl FnOnce(&mut EditHistory, &mut Network) -> Result<(), EditError>,
+    ) {
+        let mut network = Network {
+            ctx,
+            stations: &mut self.stations,
+            routes: &mut self.routes,
+            vehicles: &mut self.vehicles,
+            depots: &mut self.depots,
+            analytics: &mut self.analytics,
+            resources: &mut self.resources,
+            mode: self.mode,
+        };
+        let result = action(&mut self.history, &mut network);
+        self.editor.set_status(result);
+    }
+
+    fn execute(&mut self, ctx: &Context, command: Command) {
+        self.edit(ctx, |history, network| history.execute(command, network));
+    }
+
+    // Every game starts from the same seeded network, keeping the scene stack and CLI options.
+    fn restart(&mut self, ctx: &mut Context, mode: GameMode) {
+        let world = MetroWorld::new(ctx, self.seed, self.settings);
+        let od_path = self.od_path.take();
+        *self = MetroWorld {
+            scenes: mem::replace(&mut self.scenes, SceneHandler::new()),
+            export_dir: self.export_dir.take(),
+            logical_width: self.logical_width,
+            logical_height: self.logical_height,
+            screen_transform_rect: self.screen_transform_rect,
+            ..world
+        };
+        if let Some(path) = od_path
+            && let Err(e) = self.load_od_matrix(&path)
+        {
+            eprintln!("Failed to reload OD matrix, using gravity model: {e}");
+        }
+        self.set_mode(mode);
+    }
+
+    fn handle_scene_event(&mut self, ctx: &mut Context, event: SceneEvent, previous: Settings) {
+        match event {
+            SceneEvent::Start(mode) => self.restart(ctx, mode),
+            SceneEvent::SettingsChanged => {
+                self.routes.set_line_style(self.settings.line_style);
+                self.routes.set_marking(self.settings.line_marking);
+                self.routes.set_labels_visible(self.settings.line_labels);
+                if self.settings.walking_radius != previous.walking_radius {
+                    self.stations
+                        .set_walking_radius(self.settings.walking_radius);
+                }
+                if self.settings.theme != previous.theme {
+                    self.apply_theme(ctx);
+                } else if self.settings.line_palette != previous.line_palette {
+                    self.routes.set_palette(self.settings.line_palette.colors());
+                }
+                if (self.settings.window_width, self.settings.window_height)
+                    != (previous.window_width, previous.window_height)
+                    && let Err(e) = ctx
+                        .gfx
+                        .set_drawable_size(self.settings.window_width, self.settings.window_height)
+                {
+                    eprintln!("Failed to resize window: {e}");
+                }
+                self.save_settings();
+            }
+            SceneEvent::Quit => ctx.request_quit(),
+        }
+    }
+
+    // Meshes bake their colours in, so switching themes rebuilds them.
+    fn apply_theme(&mut self, ctx: &Context) {
+        Colors::set_theme(self.settings.theme.load());
+        self.stations.set_palettes(
+            ctx,
+            ShapePalette::new(Colors::station_fill(), Colors::station_outline()),
+            ShapePalette::fill(Colors::passenger()),
+        );
+        self.vehicles.set_palette(
+            ctx,
+            ShapePalette::new(Colors::cargo_fill(), Colors::cargo_outline()),
+        );
+        self.depots.set_palette(
+            ctx,
+            ShapePalette::new(Colors::depot_fill(), Colors::depot_outline()),
+        );
+        self.routes.set_palette(self.settings.line_palette.colors());
+    }
+
+    fn save_settings(&self) {
+        if let Err(e) = self.settings.save() {
+            eprintln!("Failed to save settings: {e}");
+        }
+    }
+
+    fn set_mode(&mut self, mode: GameMode) {
+        self.mode = mode;
+        self.vehicles
+            .set_max_count(if mode.has_unlimited_resources() {
+                u32::MAX
+            } else {
+                MAX_VEHICLES
+            });
+    }
+
+    fn to_logical(&self, ctx: &Context, x: f32, y: f32) -> Vec2 {
+        let (width, height) = ctx.gfx.drawable_size();
+        Vec2::new(
+            self.screen_transform_rect.x + x / width * self.screen_transform_rect.w,
+            self.screen_transform_rect.y + y / height * self.screen_transform_rect.h,
+        )
+    }
+
     fn maintain_screen_aspect_ratio(&mut self, width: f32, height: f32) {
         let scale_x = width / self.logical_width;
         let scale_y = height / self.logical_height;
@@ -111,17 +351,218 @@ impl MetroWorld {
 impl EventHandler<GameError> for MetroWorld {
     fn resize_event(&mut self, ctx: &mut Context, width: f32, height: f32) -> GameResult {
         self.maintain_screen_aspect_ratio(width, height);
+        self.settings.window_width = width;
+        self.settings.window_height = height;
+        Ok(())
+    }
+
+    fn key_down_event(
+        &mut self,
+        ctx: &mut Context,
+        input: KeyInput,
+        _repeated: bool,
+    ) -> GameResult {
+        if let Some(keycode) = input.keycode {
+            let scene = self.scenes.current();
+            if scene == Scene::Statistics {
+                self.stats_screen.handle_key(keycode, &self.analytics);
+            }
+            let previous = self.settings;
+            if let Some(event) = self.scenes.handle_key(keycode, &mut self.settings) {
+                self.handle_scene_event(ctx, event, previous);
+            }
+            if scene != Scene::InGame || self.scenes.current() != Scene::InGame {
+                return Ok(());
+            }
+            if self.heatmap.handle_key(keycode) || self.debug_overlay.handle_key(keycode) {
+                return Ok(());
+            }
+            if self.timetable_editor.handle_key(keycode, &mut self.routes) {
+                return Ok(());
+            }
+            let ctrl = input.mods.contains(KeyMods::CTRL);
+            if self.mode == GameMode::Creative {
+                let selected = self.inspector.selected_station(&self.stations);
+                let cursor = self.editor.cursor();
+                if let Some(command) = self.creative.handle_key(
+                    keycode,
+                    cursor,
+                    selected,
+                    &self.stations,
+                    &self.depots,
+                ) {
+                    self.execute(ctx, command);
+                }
+            }
+            match keycode {
+                KeyCode::Z if ctrl && input.mods.contains(KeyMods::SHIFT) => {
+                    self.edit(ctx, |history, network| history.redo(network).map(|_| ()))
+                }
+                KeyCode::Z if ctrl => {
+                    self.edit(ctx, |history, network| history.undo(network).map(|_| ()))
+                }
+                KeyCode::Y if ctrl => {
+                    self.edit(ctx, |history, network| history.redo(network).map(|_| ()))
+                }
+                KeyCode::V => {
+                    if let Some(station) = self.inspector.selected_station(&self.stations) {
+                        let route = self
+                            .routes
+                            .iter()
+                            .find(|route| route.stops().iter().any(|stop| stop.index() == station));
+                        match route {
+                            Some(route) => {
+                                let command = Command::PlaceVehicle {
+                                    route: route.id(),
+                                    station,
+                                };
+                                self.execute(ctx, command);
+                            }
+                            None => self.editor.set_status(Err(EditError::InvalidStop)),
+                        }
+                    }
+                }
+                KeyCode::U => {
+                    if let Some(Selection::Vehicle(vehicle)) = self.inspector.selection() {
+                        self.execute(ctx, Command::AddCarriage { vehicle });
+                    }
+                }
+                KeyCode::B => {
+                    let breakdowns = self.vehicles.breakdowns_mut();
+                    breakdowns.set_enabled(!breakdowns.is_enabled());
+                }
+                KeyCode::H => self.hard_mode = !self.hard_mode,
+                KeyCode::C => {
+                    self.settings.line_style = self.routes.line_style().next();
+                    self.routes.set_line_style(self.settings.line_style);
+                }
+                KeyCode::E => self.export_metrics(),
+                KeyCode::P => {
+                    let policy = self.stations.boarding_policy().next();
+                    self.stations.set_boarding_policy(policy);
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn quit_event(&mut self, _ctx: &mut Context) -> GameResult<bool> {
+        if self.export_dir.is_some() {
+            self.export_metrics();
+        }
+        self.save_settings();
+        Ok(false)
+    }
+
+    fn mouse_button_down_event(
+        &mut self,
+        ctx: &mut Context,
+        button: MouseButton,
+        x: f32,
+        y: f32,
+    ) -> GameResult {
+        if self.scenes.current() != Scene::InGame {
+            return Ok(());
+        }
+        let position = self.to_logical(ctx, x, y);
+        match button {
+            MouseButton::Left => {
+                self.inspector
+                    .select(position, &self.stations, &self.vehicles);
+                let move_station =
+                    self.mode.allows_station_edits() && ctx.keyboard.is_mod_active(KeyMods::CTRL);
+                self.editor
+                    .begin_drag(position, move_station, &self.stations, &self.vehicles);
+            }
+            MouseButton::Right => {
+                if let Some(command) = self
+                    .editor
+                    .remove_at(position, &self.stations, &self.routes)
+                {
+                    self.execute(ctx, command);
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn mouse_button_up_event(
+        &mut self,
+        ctx: &mut Context,
+        button: MouseButton,
+        x: f32,
+        y: f32,
+    ) -> GameResult {
+        if button == MouseButton::Left && self.scenes.current() == Scene::InGame {
+            let position = self.to_logical(ctx, x, y);
+            let insert = ctx.keyboard.is_mod_active(KeyMods::SHIFT);
+            if let Some(command) =
+                self.editor
+                    .end_drag(position, insert, &self.stations, &self.routes)
+            {
+                self.execute(ctx, command);
+            }
+        }
+        Ok(())
+    }
+
+    fn mouse_motion_event(
+        &mut self,
+        ctx: &mut Context,
+        x: f32,
+        y: f32,
+        _dx: f32,
+        _dy: f32,
+    ) -> GameResult {
+        let position = self.to_logical(ctx, x, y);
+        if let Some((station, position)) = self.editor.move_cursor(position) {
+            self.edit(ctx, |_, network| network.move_station(station, position));
+        }
         Ok(())
     }
 
     fn update(&mut self, ctx: &mut Context) -> GameResult {
-        let delta = ctx.time.delta().as_secs_f32();
+        self.scenes.update(ctx.time.delta().as_secs_f32());
+        if self.scenes.current() != Scene::InGame {
+            return Ok(());
+        }
+        let tick_start = Instant::now();
+        let delta = ctx.time.delta().as_secs_f32() * self.settings.game_speed;
 
-        self.stations.update(&mut self.rng, delta);
+        self.routing.refresh(&mut self.stations, &self.routes);
+        self.stations
+            .update(&mut self.rng, &self.routing, &self.day_cycle, delta);
         self.routes.update(&ctx, &self.stations, delta);
-        self.vehicles.update(delta, &self.routes, &mut self.stations);
+        self.vehicles.update(
+            delta,
+            &mut self.routes,
+            &mut self.stations,
+            &self.routing,
+            &self.depots,
+            &mut self.rng,
+        );
 
         self.time += delta;
+        self.analytics
+            .update(&mut self.stations, &self.vehicles, delta);
+        self.day_cycle.update(delta);
+
+        self.satisfaction
+            .update(self.stations.delivered(), self.stations.abandoned());
+        if self.mode.has_game_over() {
+            if self.stations.is_overcrowded() {
+                self.game_over = Some("A station was overcrowded for too long.");
+            } else if self.hard_mode && self.satisfaction.score() < HARD_MODE_MIN_SATISFACTION {
+                self.game_over = Some("Too many passengers gave up on the network.");
+            }
+            if self.game_over.is_some() {
+                self.scenes.transition(Transition::Push(Scene::GameOver));
+            }
+        }
+        self.debug_overlay
+            .record_tick(tick_start.elapsed().as_secs_f32());
 
         Ok(())
     }
@@ -134,19 +575,82 @@ impl EventHandler<GameError> for MetroWorld {
         canvas.draw(
             &Quad,
             DrawParam::default()
-                .color(Colors::background())
+                .color(Colors::background(self.day_cycle.daylight()))
                 .scale([self.logical_width, self.logical_height]),
         );
 
+        self.depots.draw(&mut canvas);
         for route in self.routes.iter_mut() {
             route.draw(&ctx, &mut canvas);
             for metro_id in self.vehicles.metros_on_route(route.id()) {
-                self.vehicles
-                    .get(metro_id)
-                    .draw(&mut canvas, &self.vehicles.shapes(), route.color());
+                self.vehicles.get(metro_id).draw(
+                    &mut canvas,
+                    &self.vehicles.shapes(),
+                    Colors::vehicle(route.color()),
+                );
             }
         }
         self.stations.draw(&mut canvas, &self.vehicles);
+        self.heatmap
+            .draw(ctx, &mut canvas, &self.routes, &self.stations);
+        self.debug_overlay.draw(
+            ctx,
+            &mut canvas,
+            self.screen_transform_rect,
+            &self.routes,
+            &self.vehicles,
+        );
+        self.timetable_editor
+            .draw(ctx, &mut canvas, self.screen_transform_rect, &self.routes);
+        self.inspector.draw(
+            ctx,
+            &mut canvas,
+            self.screen_transform_rect,
+            &self.stations,
+            &self.routes,
+            &self.vehicles,
+        );
+        self.editor.draw(
+            ctx,
+            &mut canvas,
+            self.screen_transform_rect,
+            &self.resources,
+            self.mode,
+            &self.history,
+        );
+        if self.mode == GameMode::Creative {
+            self.creative.draw(
+                ctx,
+                &mut canvas,
+                self.screen_transform_rect,
+                self.inspector.selected_station(&self.stations),
+                &self.stations,
+            );
+        }
+        draw_hud(
+            ctx,
+            &mut canvas,
+            self.screen_transform_rect,
+            &self.satisfaction,
+            &self.day_cycle,
+            self.mode,
+            self.hard_mode,
+        );
+        if self.scenes.current() == Scene::Statistics {
+            self.stats_screen.draw(
+                ctx,
+                &mut canvas,
+                self.screen_transform_rect,
+                &self.analytics,
+            );
+        }
+        self.scenes.draw(
+            ctx,
+            &mut canvas,
+            self.screen_transform_rect,
+            &self.settings,
+            self.game_over,
+        );
 
         canvas.finish(ctx)
     }